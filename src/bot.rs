@@ -0,0 +1,362 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use specs::prelude::*;
+use rltk::{Point, DistanceAlg, BaseMap};
+use crate::{headless::{Simulation, Command}, state::{Seed, Turn}, dancing::Dance, systems::{effects::Effect, monster_ai_system::is_good_start_position}};
+use crate::{Map, MapPather, WallOnlyMapPather, TileType, Position, Viewshed, Monster, Item, InBackpack, Name, Health, Stamina, Poise, CombatStats};
+use crate::{ProvidesHealing, ProvidesStamina, ProvidesPoise, Ranged, InflictsDamage, CausesConfusion, AreaOfEffect, Equippable, Equipped, EquipmentSlot, MeleePowerBonus, DefenceBonus, CanDoDances, Dancing, HasAggroedMosters, SpreadsLiquid};
+
+// A run that goes on this long is stuck, usually walking into something that won't move.
+const MAX_ACTIONS: u32 = 5000;
+// How far out of its way the bot goes for an item it can see.
+const MAX_ITEM_DETOUR: usize = 10;
+
+/// How a bot run ended. With no outcome the bot gave up before the game was over.
+pub struct RunResult {
+    pub seed: Seed,
+    pub depth: i32,
+    pub turn: Turn,
+    pub outcome: Option<(bool, String)>
+}
+
+pub struct BatchReport {
+    pub runs: Vec<RunResult>
+}
+
+/// Plays a whole game with the given seed. The bot isn't clever, but it plays the same way
+/// every time, so changes to the game show up as changes in how it does.
+pub fn play(seed: Seed) -> Result<RunResult, String> {
+    let mut sim = Simulation::new(seed)?;
+    for _ in 0..MAX_ACTIONS {
+        if sim.is_over() {
+            break;
+        }
+        let command = choose(&sim.state.ecs);
+        sim.command(&command)?;
+    }
+    let report = sim.report();
+    Ok(RunResult { seed, depth: report.depth, turn: report.turn, outcome: report.outcome })
+}
+
+/// Plays a game for each seed in turn, handing each result to `finished` as soon as it's known.
+pub fn play_batch<F: FnMut(&RunResult)>(first_seed: Seed, runs: u64, mut finished: F) -> Result<BatchReport, String> {
+    let mut results = Vec::new();
+    for seed in first_seed..first_seed + runs {
+        let result = play(seed)?;
+        finished(&result);
+        results.push(result);
+    }
+    Ok(BatchReport { runs: results })
+}
+
+/// Picks what to do next: look after itself, fight whatever's next to it once there's a fight,
+/// dance to keep its poise up, pick up anything nearby, and otherwise head for the stairs. The bot
+/// knows where the stairs are from the start.
+fn choose(ecs: &World) -> Command {
+    let player = *ecs.fetch::<Entity>();
+    let pos = *ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let healths = ecs.read_storage::<Health>();
+    let staminas = ecs.read_storage::<Stamina>();
+    let poises = ecs.read_storage::<Poise>();
+
+    let health = healths.get(player).map_or((1, 1), |h| (h.health, h.max_health));
+    let stamina = staminas.get(player).map_or((1, 1), |s| (s.stamina, s.max_stamina));
+    let poise = poises.get(player).map_or((1, 1), |p| (p.poise, p.max_poise));
+
+    if health.0 < health.1 / 2 {
+        if let Some(item) = carried::<ProvidesHealing>(ecs, player) {
+            return Command::Use { item, target: None };
+        }
+    }
+    if poise.0 < poise.1 / 2 {
+        if let Some(item) = carried::<ProvidesPoise>(ecs, player) {
+            return Command::Use { item, target: None };
+        }
+    }
+    if stamina.0 < stamina.1 / 3 {
+        if let Some(item) = carried::<ProvidesStamina>(ecs, player) {
+            return Command::Use { item, target: None };
+        }
+    }
+    if let Some(item) = better_equipment(ecs, player) {
+        return Command::Use { item, target: None };
+    }
+
+    // Without a weapon, bumping into things does nothing, so there's no fighting until it finds one.
+    let armed = ecs.read_storage::<Equipped>().join().any(|e| e.owner == player && e.slot == EquipmentSlot::Melee);
+    let monsters = visible_monsters(ecs, player, pos);
+    let adjacent: Vec<&(Entity, Point)> = monsters.iter().filter(|(_, at)| distance(pos, *at) < 1.5).collect();
+    // Monsters only dance at the player until it attacks one of them, and then they all come for it,
+    // so it doesn't start a fight. Once there is one, it hits whatever it can hurt.
+    let aggroed = ecs.read_storage::<HasAggroedMosters>().get(player).is_some();
+    if armed && aggroed {
+        // Finish off whatever's closest to dead.
+        let weakest = adjacent.iter().filter(|(monster, _)| hit(ecs, player, *monster) > 0).min_by_key(|(monster, _)| healths.get(*monster).map_or(0, |h| h.health));
+        if let Some((_, at)) = weakest {
+            return step(pos, *at);
+        }
+    }
+    if poise.0 < poise.1 * 2 / 3 {
+        if let Some(dance) = best_dance(ecs, player, pos, stamina.0) {
+            return Command::Dance { dance };
+        }
+        // Step somewhere there's room, if that's what's stopping it.
+        let room = neighbours(pos).into_iter()
+            .find(|at| !map.blocked[map.point_idx(at)] && best_dance(ecs, player, *at, stamina.0).is_some());
+        if let Some(at) = room {
+            return step(pos, at);
+        }
+        // Dancing takes stamina too, so have a coffee if that's all that's stopping it.
+        if best_dance(ecs, player, pos, stamina.1).is_some() {
+            if let Some(item) = carried::<ProvidesStamina>(ecs, player) {
+                return Command::Use { item, target: None };
+            }
+        }
+    }
+    if let Some(&(monster, at)) = monsters.first() {
+        if let Some(item) = missile_for(ecs, player, pos, monster, at, aggroed) {
+            return Command::Use { item, target: Some(at) };
+        }
+    }
+    // Attacking takes stamina, so get it back while there's nothing to fight.
+    if adjacent.is_empty() && stamina.0 < stamina.1 / 2 {
+        return Command::Rest { turns: 1 };
+    }
+
+    let items = ecs.read_storage::<Item>();
+    let positions = ecs.read_storage::<Position>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    if (&items, &positions).join().any(|(_, p)| p.x == pos.x && p.y == pos.y) {
+        return Command::PickUp;
+    }
+    if let Some(viewshed) = viewsheds.get(player) {
+        // Finding a weapon comes first, however far away it is.
+        let weapons = ecs.read_storage::<MeleePowerBonus>();
+        let entities = ecs.entities();
+        let nearest_item = (&entities, &items, &positions).join()
+            .map(|(item, _, p)| (!armed && weapons.get(item).is_some(), Point::new(p.x, p.y)))
+            .filter(|(_, at)| viewshed.visible_tiles.contains(at))
+            .filter_map(|(needed, at)| path(&map, &MapPather::new(&map, at, false), pos, at).map(|steps| (needed, steps)))
+            .filter(|(needed, steps)| *needed || steps.len() <= MAX_ITEM_DETOUR)
+            .min_by_key(|(needed, steps)| (!needed, steps.len()))
+            .map(|(_, steps)| steps);
+        if let Some(steps) = nearest_item {
+            return step(pos, steps[0]);
+        }
+    }
+
+    let stairs = match map.tiles.iter().position(|tile| *tile == TileType::DownStairs) {
+        Some(idx) => Point::new(idx as i32 % map.width, idx as i32 / map.width),
+        None => return Command::Rest { turns: 1 }
+    };
+    if stairs == pos {
+        return Command::Descend;
+    }
+    // Go around anything in the way if possible, and through it if not.
+    let steps = path(&map, &MapPather::new(&map, stairs, false), pos, stairs)
+        .or_else(|| path(&map, &WallOnlyMapPather::new(&map), pos, stairs));
+    match steps {
+        Some(steps) => step(pos, steps[0]),
+        None => Command::Rest { turns: 1 }
+    }
+}
+
+fn distance(a: Point, b: Point) -> f32 {
+    DistanceAlg::Pythagoras.distance2d(a, b)
+}
+
+fn neighbours(pos: Point) -> Vec<Point> {
+    (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| Point::new(pos.x + dx, pos.y + dy)))
+        .filter(|at| *at != pos)
+        .collect()
+}
+
+fn step(from: Point, to: Point) -> Command {
+    Command::Move { dx: to.x - from.x, dy: to.y - from.y }
+}
+
+/// The steps from one point to another, not counting the first.
+fn path(map: &Map, pather: &dyn BaseMap, from: Point, to: Point) -> Option<Vec<Point>> {
+    let path = rltk::a_star_search(map.point_idx(&from) as i32, map.point_idx(&to) as i32, pather);
+    if path.success && path.steps.len() > 1 {
+        Some(path.steps[1..].iter().map(|&idx| Point::new(idx as i32 % map.width, idx as i32 / map.width)).collect())
+    } else {
+        None
+    }
+}
+
+/// The name of the first thing the player is carrying with a component.
+fn carried<C: Component>(ecs: &World, player: Entity) -> Option<String> {
+    let backpack = ecs.read_storage::<InBackpack>();
+    let names = ecs.read_storage::<Name>();
+    let with = ecs.read_storage::<C>();
+    (&backpack, &names, &with).join()
+        .find(|(bp, _, _)| bp.owner == player)
+        .map(|(_, name, _)| name.name.to_string())
+}
+
+fn equipment_bonus(ecs: &World, item: Entity) -> i32 {
+    let power = ecs.read_storage::<MeleePowerBonus>().get(item).map_or(0, |b| b.power);
+    let defence = ecs.read_storage::<DefenceBonus>().get(item).map_or(0, |b| b.defence);
+    power + defence
+}
+
+/// Something carried that's better than what's equipped in its slot.
+fn better_equipment(ecs: &World, player: Entity) -> Option<String> {
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let names = ecs.read_storage::<Name>();
+    let equippables = ecs.read_storage::<Equippable>();
+    let equipped = ecs.read_storage::<Equipped>();
+    (&entities, &backpack, &names, &equippables).join()
+        .filter(|(item, bp, _, _)| bp.owner == player && equipped.get(*item).is_none())
+        .find(|(item, _, _, equippable)| {
+            let current = (&entities, &equipped).join()
+                .find(|(_, e)| e.owner == player && e.slot == equippable.slot)
+                .map_or(0, |(e, _)| equipment_bonus(ecs, e));
+            equipment_bonus(ecs, *item) > current
+        })
+        .map(|(_, _, name, _)| name.name.to_string())
+}
+
+/// The monsters the player can see, nearest first.
+fn visible_monsters(ecs: &World, player: Entity, pos: Point) -> Vec<(Entity, Point)> {
+    let entities = ecs.entities();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let viewshed = match viewsheds.get(player) {
+        Some(viewshed) => viewshed,
+        None => return Vec::new()
+    };
+    let mut seen: Vec<(Entity, Point)> = (&entities, &monsters, &positions).join()
+        .map(|(e, _, p)| (e, Point::new(p.x, p.y)))
+        .filter(|(_, at)| *at != pos && viewshed.visible_tiles.contains(at))
+        .collect();
+    seen.sort_by(|a, b| distance(pos, a.1).partial_cmp(&distance(pos, b.1)).unwrap());
+    seen
+}
+
+/// How much damage the player does to a monster with one blow, which is none without a weapon.
+fn hit(ecs: &World, player: Entity, monster: Entity) -> i32 {
+    let stats = ecs.read_storage::<CombatStats>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let bonuses = ecs.read_storage::<MeleePowerBonus>();
+    let entities = ecs.entities();
+    let weapon = (&entities, &equipped).join().find(|(_, e)| e.owner == player && e.slot == EquipmentSlot::Melee);
+    match weapon {
+        Some((weapon, _)) => stats.get(player).map_or(0, |s| s.power) + bonuses.get(weapon).map_or(0, |b| b.power)
+            - stats.get(monster).map_or(0, |s| s.defence),
+        None => 0
+    }
+}
+
+/// Something to throw at a monster that's dancing, or too tough to kill in one hit, as long as
+/// it won't catch the player too. Liquid is only worth throwing under a dancer's feet, and
+/// anything that does damage waits until there's already a fight.
+fn missile_for(ecs: &World, player: Entity, pos: Point, monster: Entity, at: Point, aggroed: bool) -> Option<String> {
+    let healths = ecs.read_storage::<Health>();
+    let hit = hit(ecs, player, monster);
+    let dancing = ecs.read_storage::<Dancing>().get(monster).is_some();
+    if !dancing && !matches!(healths.get(monster), Some(h) if h.health > hit) {
+        return None;
+    }
+
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackpack>();
+    let names = ecs.read_storage::<Name>();
+    let ranged = ecs.read_storage::<Ranged>();
+    let damages = ecs.read_storage::<InflictsDamage>();
+    let confusions = ecs.read_storage::<CausesConfusion>();
+    let areas = ecs.read_storage::<AreaOfEffect>();
+    let liquids = ecs.read_storage::<SpreadsLiquid>();
+    (&entities, &backpack, &names, &ranged).join()
+        .filter(|(_, bp, _, r)| bp.owner == player && distance(pos, at) <= r.range as f32)
+        .filter(|(item, _, _, _)| (aggroed && damages.get(*item).is_some()) || confusions.get(*item).is_some() || (dancing && liquids.get(*item).is_some()))
+        .find(|(item, _, _, _)| !matches!(areas.get(*item), Some(a) if distance(pos, at) <= a.radius as f32))
+        .map(|(_, _, name, _)| name.name.to_string())
+}
+
+fn poise_gained(dance: &Dance) -> i32 {
+    dance.steps.iter().filter_map(|step| match step.effect {
+        Some(Effect::SelfPoise { poise }) => Some(poise),
+        _ => None
+    }).sum()
+}
+
+/// The dance that gives the player the most poise back, of those it has the stamina and room for.
+fn best_dance(ecs: &World, player: Entity, pos: Point, stamina: i32) -> Option<String> {
+    let map = ecs.fetch::<Map>();
+    let dancers = ecs.read_storage::<Dancing>();
+    let can_do_dances = ecs.read_storage::<CanDoDances>();
+    can_do_dances.get(player)?.dances.iter()
+        .filter(|dance| poise_gained(dance) > 0)
+        .filter(|dance| dance.stamina_per_step * dance.steps.len() as i32 <= stamina)
+        .filter(|dance| is_good_start_position(&pos, dance, &map, &dancers))
+        .max_by_key(|dance| poise_gained(dance))
+        .map(|dance| dance.name.clone())
+}
+
+impl fmt::Display for RunResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.outcome {
+            Some((true, _)) => write!(f, "seed {}: won at turn {}", self.seed, self.turn),
+            Some((false, reason)) => write!(f, "seed {}: lost on floor {} at turn {}: {}", self.seed, self.depth, self.turn, reason),
+            None => write!(f, "seed {}: gave up on floor {} at turn {}", self.seed, self.depth, self.turn)
+        }
+    }
+}
+
+fn percent(count: usize, total: usize) -> f64 {
+    100.0 * count as f64 / total as f64
+}
+
+impl fmt::Display for BatchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.runs.len();
+        if total == 0 {
+            return writeln!(f, "no runs");
+        }
+        let won = self.runs.iter().filter(|r| matches!(r.outcome, Some((true, _)))).count();
+        let lost = self.runs.iter().filter(|r| matches!(r.outcome, Some((false, _)))).count();
+        let gave_up = total - won - lost;
+        let mut causes: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut lost_on: BTreeMap<i32, usize> = BTreeMap::new();
+        for run in self.runs.iter() {
+            if let Some((false, reason)) = &run.outcome {
+                *causes.entry(reason).or_insert(0) += 1;
+                *lost_on.entry(run.depth).or_insert(0) += 1;
+            }
+        }
+
+        writeln!(f, "{} runs", total)?;
+        writeln!(f, "won {} ({:.1}%), lost {} ({:.1}%), gave up {} ({:.1}%)",
+            won, percent(won, total), lost, percent(lost, total), gave_up, percent(gave_up, total))?;
+        writeln!(f, "average floor reached: {:.2}", self.runs.iter().map(|r| r.depth as f64).sum::<f64>() / total as f64)?;
+        writeln!(f, "average turns: {:.0}", self.runs.iter().map(|r| r.turn as f64).sum::<f64>() / total as f64)?;
+        writeln!(f, "causes of defeat:")?;
+        for (reason, count) in causes {
+            writeln!(f, "  {:<24} {} ({:.1}%)", reason, count, percent(count, total))?;
+        }
+        writeln!(f, "defeats by floor:")?;
+        for (depth, count) in lost_on {
+            writeln!(f, "  floor {:<18} {} ({:.1}%)", depth, count, percent(count, total))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::play;
+
+    // Comparing batches before and after a change only means something if a seed always plays out the same.
+    #[test]
+    fn same_seed_plays_the_same() {
+        let first = play(3).unwrap();
+        let second = play(3).unwrap();
+        assert_eq!((first.depth, first.turn, first.outcome), (second.depth, second.turn, second.outcome));
+        assert!(first.turn > 1);
+    }
+}
@@ -19,7 +19,7 @@ pub fn cell_info(cell: &Point, ecs: &World) -> Vec<String> {
         if position.x == cell.x && position.y == cell.y && map.visible_tiles[idx] {
             let mut name = name.name.to_string();
             if let Some(dancing) = dancing.get(entity) {
-                name = format!("{} (doing the {} dance)", name, dancing.dance.name());
+                name = format!("{} (doing the {} dance)", name, dancing.dance.name);
             }
             let mut ai_info = Vec::new();
             if let Some(MonsterAI { state, .. }) = monster_ai.get(entity) {
@@ -45,35 +45,6 @@ pub fn cell_info(cell: &Point, ecs: &World) -> Vec<String> {
             if ai_info.len() > 0 {
                 name = format!("{} ({})", name, ai_info.join(", "));
             }
-            #[cfg(debug_assertions)]
-            if let Some(MonsterAI { state, .. }) = monster_ai.get(entity) {
-                match state {
-                    MonsterAIState::WAITING => {
-                        name = format!("{} [W]", name);
-                    }
-                    MonsterAIState::RESTING => {
-                        name = format!("{} [R]", name);
-                    }
-                    MonsterAIState::AGGRESSIVE => {
-                        name = format!("{} [A]", name);
-                    }
-                    MonsterAIState::DANCING { .. } => {
-                        name = format!("{} [D]", name);
-                    }
-                    MonsterAIState::MOVING { goal: MovementGoal::Flee, .. } => {
-                        name = format!("{} [MF]", name);
-                    }
-                    MonsterAIState::MOVING { goal: MovementGoal::SeekEnemy, .. } => {
-                        name = format!("{} [MSe]", name);
-                    }
-                    MonsterAIState::MOVING { goal: MovementGoal::GoDance { .. }, .. } => {
-                        name = format!("{} [MGd]", name);
-                    }
-                    MonsterAIState::MOVING { goal: MovementGoal::InvestigateNoise { .. }, .. } => {
-                        name = format!("{} [MIn]", name);
-                    }
-                }
-            }
             items.push(name);
         }
     }
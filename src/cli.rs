@@ -0,0 +1,54 @@
+use crate::state::Seed;
+
+pub struct Args {
+    pub headless_script: Option<String>,
+    pub replay: Option<String>,
+    pub seed: Option<Seed>,
+    pub profile: bool,
+    // How many games for the bot to play, starting from the seed.
+    pub bot_runs: Option<u64>
+}
+
+pub fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        headless_script: None,
+        replay: None,
+        seed: None,
+        profile: false,
+        bot_runs: None
+    };
+
+    let mut it = std::env::args().skip(1);
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--headless" => {
+                args.headless_script = Some(it.next().ok_or("--headless needs a script file")?);
+            }
+            "--replay" => {
+                args.replay = Some(it.next().ok_or("--replay needs a replay file")?);
+            }
+            "--seed" => {
+                let seed = it.next().ok_or("--seed needs a number")?;
+                args.seed = Some(seed.parse().map_err(|_| format!("bad seed {}", seed))?);
+            }
+            "--profile" => {
+                args.profile = true;
+            }
+            "--bot" => {
+                let runs = it.next().ok_or("--bot needs a number of runs")?;
+                args.bot_runs = Some(runs.parse().map_err(|_| format!("bad number of runs {}", runs))?);
+            }
+            _ => return Err(format!("unknown argument {}", arg))
+        }
+    }
+
+    if args.replay.is_some() && (args.headless_script.is_some() || args.seed.is_some()) {
+        return Err("--replay can't be used with --headless or --seed".to_string());
+    }
+
+    if args.bot_runs.is_some() && (args.headless_script.is_some() || args.replay.is_some() || args.profile) {
+        return Err("--bot can't be used with --headless, --replay or --profile".to_string());
+    }
+
+    Ok(args)
+}
@@ -1,11 +1,12 @@
 use std::collections::HashSet;
+use std::fmt;
 use specs::prelude::*;
 use specs_derive::*;
 use serde::{Serialize, Deserialize};
 use specs::saveload::{Marker, ConvertSaveload, SimpleMarker, SimpleMarkerAllocator};
 use rltk::{RGB, Point};
 use specs::error::NoError;
-use crate::{dancing::{Dance, Step}, systems::effects::Effect, liquids::Liquid, factions::Faction, systems::monster_ai_system::MonsterAIState, Turn};
+use crate::{dancing::{Dance, Step}, systems::effects::Effect, liquids::Liquid, factions::Faction, systems::monster_ai_system::MonsterAIState, Turn, Seed};
 
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Position {
@@ -227,12 +228,13 @@ pub struct SerializeMe;
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct SerializationHelper {
     pub map: super::map::Map,
-    pub player_log: super::gamelog::PlayerLog
-}
-
-#[derive(Component, ConvertSaveload, Clone)]
-pub struct ParticleLifetime {
-    pub lifetime_ms: f32
+    pub player_log: super::gamelog::PlayerLog,
+    pub seed: Seed,
+    pub input_record: super::replay::InputRecord,
+    pub turn: Turn,
+    pub rng: super::random::RandomNumberGenerator,
+    pub key_state: super::player::KeyState,
+    pub stats: super::stats::RunStats
 }
 
 #[derive(Component, Clone)]
@@ -243,6 +245,47 @@ pub struct MovingAutomatically {
     pub left_clearance: i32
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MovingAutomaticallyData<M> {
+    pub direction: Point,
+    pub seen_entities: Vec<M>,
+    pub right_clearance: i32,
+    pub left_clearance: i32
+}
+
+// Entities seen while moving may have died since, so only the ones that are still saved are kept.
+impl<M: Marker + Serialize> ConvertSaveload<M> for MovingAutomatically
+where
+    for<'de> M: Deserialize<'de>
+{
+    type Data = MovingAutomaticallyData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>
+    {
+        Ok(MovingAutomaticallyData {
+            direction: self.direction,
+            seen_entities: self.seen_entities.iter().filter_map(|e| ids(*e)).collect(),
+            right_clearance: self.right_clearance,
+            left_clearance: self.left_clearance
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>
+    {
+        Ok(MovingAutomatically {
+            direction: data.direction,
+            seen_entities: data.seen_entities.into_iter().filter_map(&mut ids).collect(),
+            right_clearance: data.right_clearance,
+            left_clearance: data.left_clearance
+        })
+    }
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct CanDoDances {
     pub dances: Vec<Dance>,
@@ -283,7 +326,20 @@ pub struct EffectRequest {
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct Awestruck {
     pub poise: i32,
-    pub reason: String
+    pub reason: String,
+    pub by: Entity
+}
+
+// Who last hurt this, and who last awed it, so the credit for it dying or being shamed goes to
+// them. They may be gone by then.
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct HurtBy {
+    pub by: Entity
+}
+
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct AwedBy {
+    pub by: Entity
 }
 
 #[derive(Component, ConvertSaveload, Clone)]
@@ -328,6 +384,25 @@ impl Stamina {
 #[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Resting {}
 
+// Gains `speed` energy every turn, and gets a turn once it has enough.
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct Energy {
+    pub energy: i32,
+    pub speed: i32
+}
+
+// Whatever has this is acting now, and pays `cost` energy when its turn ends.
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct MyTurn {
+    pub cost: i32
+}
+
+// Slows whoever has this equipped.
+#[derive(Component, ConvertSaveload, Clone)]
+pub struct SpeedPenalty {
+    pub penalty: i32
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct MakeNoise {
     pub location: Point,
@@ -353,7 +428,7 @@ pub struct MakesNoise {
     pub description: String
 }
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct ItemUseInProgress {
     pub item: Entity,
     pub targets_centre: Point,
@@ -361,6 +436,64 @@ pub struct ItemUseInProgress {
     pub targets: Vec<Entity>
 }
 
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ItemUseInProgressData<M> {
+    pub item: M,
+    pub targets_centre: Point,
+    pub target_tiles: Vec<Point>,
+    pub targets: Vec<M>
+}
+
+/// What a component refers to that isn't in the save, so the component can't be saved or loaded.
+#[derive(Debug)]
+pub struct Unsaved(pub &'static str);
+
+impl fmt::Display for Unsaved {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the {} it refers to isn't saved", self.0)
+    }
+}
+
+impl From<NoError> for Unsaved {
+    fn from(e: NoError) -> Self {
+        match e {}
+    }
+}
+
+// Targets may have died since, so only the ones still saved are kept, but there's no using an
+// item that isn't there.
+impl<M: Marker + Serialize> ConvertSaveload<M> for ItemUseInProgress
+where
+    for<'de> M: Deserialize<'de>
+{
+    type Data = ItemUseInProgressData<M>;
+    type Error = Unsaved;
+
+    fn convert_into<F>(&self, mut ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>
+    {
+        Ok(ItemUseInProgressData {
+            item: ids(self.item).ok_or(Unsaved("item"))?,
+            targets_centre: self.targets_centre,
+            target_tiles: self.target_tiles.clone(),
+            targets: self.targets.iter().filter_map(|e| ids(*e)).collect()
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, mut ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>
+    {
+        Ok(ItemUseInProgress {
+            item: ids(data.item).ok_or(Unsaved("item"))?,
+            targets_centre: data.targets_centre,
+            target_tiles: data.target_tiles,
+            targets: data.targets.into_iter().filter_map(&mut ids).collect()
+        })
+    }
+}
+
 #[derive(Component, ConvertSaveload, Clone)]
 pub struct ProvidesStamina {
     pub stamina: i32
@@ -371,57 +504,78 @@ pub struct ProvidesPoise {
     pub poise: i32
 }
 
+// Every component, declared once. Registration and saving and loading are all generated
+// from this list, so everything here goes in save files. `SimpleMarker` isn't here because
+// it's how saved entities are marked.
+macro_rules! with_components {
+    ( $callback:ident ! ( $($args:tt)* ) ) => {
+        $callback!( $($args)* [
+            Position
+            Renderable
+            Player
+            Viewshed
+            Monster
+            MonsterAI
+            Name
+            BlocksTile
+            Health
+            CombatStats
+            WantsToMelee
+            SufferDamage
+            Item
+            ProvidesHealing
+            InBackpack
+            WantsToPickupItem
+            WantsToUseItem
+            WantsToDropItem
+            WantsToUnequipItem
+            Consumable
+            Ranged
+            InflictsDamage
+            AreaOfEffect
+            Confusion
+            CausesConfusion
+            Equippable
+            Equipped
+            MeleePowerBonus
+            DefenceBonus
+            SerializationHelper
+            MovingAutomatically
+            CanDoDances
+            Dancing
+            Poise
+            EffectRequest
+            Awestruck
+            HasAggroedMosters
+            WantsToMove
+            SpreadsLiquid
+            WantsToDance
+            InFaction
+            Stamina
+            Resting
+            MakeNoise
+            Noise
+            MakesNoise
+            ItemUseInProgress
+            ProvidesStamina
+            ProvidesPoise
+            Energy
+            MyTurn
+            SpeedPenalty
+            HurtBy
+            AwedBy
+        ])
+    };
+}
+
+macro_rules! register_components {
+    ($ecs:expr, [ $( $type:ident )* ]) => {
+        $( $ecs.register::<$type>(); )*
+    };
+}
+
 pub fn setup_ecs(ecs: &mut World) {
-    ecs.register::<Position>();
-    ecs.register::<Renderable>();
-    ecs.register::<Player>();
-    ecs.register::<Viewshed>();
-    ecs.register::<Monster>();
-    ecs.register::<MonsterAI>();
-    ecs.register::<Name>();
-    ecs.register::<BlocksTile>();
-    ecs.register::<Health>();
-    ecs.register::<CombatStats>();
-    ecs.register::<WantsToMelee>();
-    ecs.register::<SufferDamage>();
-    ecs.register::<Item>();
-    ecs.register::<ProvidesHealing>();
-    ecs.register::<InBackpack>();
-    ecs.register::<WantsToPickupItem>();
-    ecs.register::<WantsToUseItem>();
-    ecs.register::<WantsToDropItem>();
-    ecs.register::<WantsToUnequipItem>();
-    ecs.register::<Consumable>();
-    ecs.register::<Ranged>();
-    ecs.register::<InflictsDamage>();
-    ecs.register::<AreaOfEffect>();
-    ecs.register::<Confusion>();
-    ecs.register::<CausesConfusion>();
-    ecs.register::<Equippable>();
-    ecs.register::<Equipped>();
-    ecs.register::<MeleePowerBonus>();
-    ecs.register::<DefenceBonus>();
-    ecs.register::<SerializationHelper>();
+    with_components!(register_components!(ecs,));
     ecs.register::<SimpleMarker<SerializeMe>>();
-    ecs.register::<ParticleLifetime>();
-    ecs.register::<MovingAutomatically>();
-    ecs.register::<CanDoDances>();
-    ecs.register::<Dancing>();
-    ecs.register::<Poise>();
-    ecs.register::<EffectRequest>();
-    ecs.register::<Awestruck>();
-    ecs.register::<HasAggroedMosters>();
-    ecs.register::<WantsToMove>();
-    ecs.register::<SpreadsLiquid>();
-    ecs.register::<WantsToDance>();
-    ecs.register::<InFaction>();
-    ecs.register::<Stamina>();
-    ecs.register::<Resting>();
-    ecs.register::<MakeNoise>();
-    ecs.register::<Noise>();
-    ecs.register::<MakesNoise>();
-    ecs.register::<ItemUseInProgress>();
-    ecs.register::<ProvidesStamina>();
-    ecs.register::<ProvidesPoise>();
     ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
 }
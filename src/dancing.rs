@@ -1,12 +1,15 @@
 use serde::{Serialize, Deserialize};
 use rltk::Point;
-use crate::{systems::effects::Effect};
+use crate::{systems::effects::Effect, stuff::StuffDefs};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const DANCES_FILE_PATH: &str = "data/dances.json";
 
 #[derive(PartialEq, Clone, Serialize, Deserialize)]
-pub enum Dance {
-    HOP,
-    JITTER,
-    CIRCLE
+pub struct Dance {
+    pub name: String,
+    pub steps: Vec<Step>,
+    pub stamina_per_step: i32
 }
 
 #[derive(PartialEq, Clone, Serialize, Deserialize)]
@@ -15,51 +18,81 @@ pub struct Step {
     pub effect: Option<Effect>
 }
 
-impl Dance {
-    pub fn name(&self) -> String {
-        match self {
-            Dance::HOP => "hop".to_string(),
-            Dance::JITTER => "jitter".to_string(),
-            Dance::CIRCLE => "circle".to_string()
-        }
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DanceDef {
+    name: String,
+    #[serde(default = "default_stamina_per_step")]
+    stamina_per_step: i32,
+    known_by: Vec<String>,
+    steps: Vec<StepDef>
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StepDef {
+    dx: i32,
+    dy: i32,
+    effect: Option<Effect>
+}
+
+fn default_stamina_per_step() -> i32 {
+    1
+}
+
+/// All the dances, with the names of the stuff that knows each one.
+pub struct DanceDefs {
+    dances: Vec<(Dance, Vec<String>)>
+}
+
+impl DanceDefs {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(stuff: &StuffDefs) -> Result<Self, String> {
+        let data = std::fs::read_to_string(DANCES_FILE_PATH).map_err(|e| format!("can't read {}: {}", DANCES_FILE_PATH, e))?;
+        DanceDefs::parse(&data, stuff).map_err(|e| format!("{}: {}", DANCES_FILE_PATH, e))
     }
 
-    pub fn steps(&self) -> Vec<Step> {
-        match self {
-            Dance::HOP => vec![
-                    step(-1, 0),
-                    step_with_effect(1, 0, Effect::Awesomeness { poise: 1 })
-            ],
-            Dance::JITTER => vec![
-                    step(-1, 0),
-                    step_with_effect(1, 0, Effect::Awesomeness { poise: 1 }),
-                    step(0, -1),
-                    step_with_effect(0, 1, Effect::Awesomeness { poise: 1 }),
-                    step(1, 0),
-                    step_with_effect(-1, 0, Effect::Awesomeness { poise: 1 }),
-                    step(0, 1),
-                    step_with_effect(0, -1, Effect::SelfPoise { poise: 2 })
-            ],
-            Dance::CIRCLE => vec![
-                    step_with_effect(1, 0, Effect::Awesomeness { poise: 1 }),
-                    step_with_effect(0, 1, Effect::Awesomeness { poise: 1 }),
-                    step_with_effect(-1, 0, Effect::Awesomeness { poise: 2 }),
-                    step_with_effect(0, -1, Effect::SelfPoise { poise: 4 })
-            ]
+    #[cfg(target_arch = "wasm32")]
+    pub fn load(stuff: &StuffDefs) -> Result<Self, String> {
+        DanceDefs::parse(include_str!("../data/dances.json"), stuff)
+    }
+
+    pub fn parse(data: &str, stuff: &StuffDefs) -> Result<Self, String> {
+        let defs: Vec<DanceDef> = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        let mut dances: Vec<(Dance, Vec<String>)> = Vec::new();
+        for def in defs {
+            if dances.iter().any(|(dance, _)| dance.name == def.name) {
+                return Err(format!("{} is defined twice", def.name));
+            }
+            if def.steps.is_empty() {
+                return Err(format!("{} has no steps", def.name));
+            }
+            if def.stamina_per_step < 0 {
+                return Err(format!("{} has negative stamina_per_step", def.name));
+            }
+            for name in def.known_by.iter() {
+                match stuff.get(name) {
+                    None => return Err(format!("{} is known by {}, but there's no stuff called that", def.name, name)),
+                    Some(stuff_def) if stuff_def.dance_descriptors.is_empty() => {
+                        return Err(format!("{} is known by {}, which has no dance_descriptors", def.name, name));
+                    }
+                    Some(_) => {}
+                }
+            }
+            let steps = def.steps.into_iter().map(|s| Step { direction: Point::new(s.dx, s.dy), effect: s.effect }).collect();
+            dances.push((Dance { name: def.name, steps, stamina_per_step: def.stamina_per_step }, def.known_by));
         }
+        Ok(DanceDefs { dances })
     }
-}
 
-fn step(dx: i32, dy: i32) -> Step {
-    Step {
-        direction: Point::new(dx, dy),
-        effect: None
+    pub fn get(&self, name: &str) -> Option<&Dance> {
+        self.dances.iter().map(|(dance, _)| dance).find(|dance| dance.name == name)
     }
-}
 
-fn step_with_effect(dx: i32, dy: i32, effect: Effect) -> Step {
-    Step {
-        direction: Point::new(dx, dy),
-        effect: Some(effect)
+    pub fn known_by(&self, name: &str) -> Vec<Dance> {
+        self.dances.iter()
+            .filter(|(_, known_by)| known_by.iter().any(|n| n == name))
+            .map(|(dance, _)| dance.clone())
+            .collect()
     }
 }
@@ -2,7 +2,7 @@ use std::cmp::{min, max};
 use std::collections::{HashSet};
 use specs::prelude::*;
 use rltk::prelude::*;
-use super::{Position, Renderable, liquids::Liquid, Dancing};
+use super::{Position, Renderable, liquids::Liquid, Dancing, systems::particle_system::ParticleBuilder};
 use super::map::{Map, TileType};
 
 pub fn draw_world(ecs: &World, ctx: &mut Rltk) {
@@ -47,6 +47,16 @@ pub fn draw_world(ecs: &World, ctx: &mut Rltk) {
             }
         }
     }
+
+    let particle_builder = ecs.fetch::<ParticleBuilder>();
+    for particle in particle_builder.particles.iter() {
+        if particle.x >= world_min_x && particle.x < world_max_x && particle.y >= world_min_y && particle.y < world_max_y {
+            let idx = map.xy_idx(particle.x, particle.y);
+            if map.visible_tiles[idx] {
+                ctx.set(particle.x - world_min_x, particle.y - world_min_y, particle.fg, bg, particle.glyph);
+            }
+        }
+    }
 }
 
 pub fn world_to_screen_points(points: &Vec<Point>, ecs: &World, ctx: &mut Rltk) -> Vec<Point> {
@@ -0,0 +1,52 @@
+use specs::prelude::{Entity};
+use specs::shrev::{EventChannel};
+use rltk::{Point};
+use super::{Name, dancing::Dance, liquids::Liquid};
+
+/// Everything that happens in the game worth telling anyone about. Systems write these into
+/// the `GameEvents` resource, and anything that wants to know about them registers a reader
+/// on it: the log turns them into text, but they're there for other uses too.
+#[derive(Clone)]
+pub enum GameEvent {
+    // A damage of 0 means the attacker couldn't hurt the target.
+    Attacked { attacker: Entity, target: Entity, damage: i32 },
+    Damaged { user: Entity, item: Entity, target: Entity, damage: i32 },
+    Confused { user: Entity, item: Entity, target: Entity },
+    Healed { entity: Entity, item: Entity, amount: i32 },
+    GainedStamina { entity: Entity, item: Entity, amount: i32 },
+    GainedPoise { entity: Entity, item: Entity, amount: i32 },
+    // The dead are deleted straight away, so this remembers who they were and where. The
+    // entity is only good for telling who it was, as is whoever last hurt them, if anyone did.
+    Died { entity: Entity, name: Name, at: Point, by: Option<Entity> },
+    Awed { entity: Entity, by: Entity, reason: String, poise: i32 },
+    CouldntDance { entity: Entity, hindrance: Hindrance },
+    StartedDance { entity: Entity, dance: Dance },
+    StoppedDancing { entity: Entity, hindrance: Hindrance, poise: i32 },
+    FailedDance { entity: Entity, poise: i32 },
+    FinishedDance { entity: Entity },
+    // Whoever brought it to this, if anyone did: who last hurt it, or who last awed it.
+    Fled { entity: Entity, reason: FleeReason, by: Option<Entity> },
+    Slipped { entity: Entity, liquid: Liquid },
+    // How many floor tiles an item someone used stained with a liquid they weren't stained with already.
+    Stained { entity: Entity, tiles: u32 },
+    PickedUp { entity: Entity, item: Entity },
+    Dropped { entity: Entity, item: Entity },
+    Equipped { entity: Entity, item: Entity },
+    Unequipped { entity: Entity, item: Entity }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Hindrance {
+    Confused,
+    Tired,
+    Intimidated
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum FleeReason {
+    Hurt,
+    Tired,
+    Shamed
+}
+
+pub type GameEvents = EventChannel<GameEvent>;
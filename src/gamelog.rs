@@ -1,14 +1,39 @@
 use specs::prelude::{Entity};
+use specs::shrev::{ReaderId};
 use serde::{Serialize, Deserialize};
-use rltk::{Point};
+use rltk::{Point, RGB};
+use std::fs;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use super::events::{GameEvent, GameEvents};
+use super::state::{Turn, Seed};
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PlayerLog {
-    pub entries: Vec<String>
+    pub entries: Vec<Entry>,
+    // Entries too old to keep in the log, still there to export. Not saved.
+    #[serde(skip)]
+    pub earlier: Vec<Entry>
+}
+
+/// What a message is about, so it can be coloured and the full log can be filtered by it.
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum Category { Combat, Dance, Noise, Item, System }
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Entry {
+    // The turn the message first came up on.
+    pub turn: Turn,
+    pub category: Category,
+    pub message: String,
+    // How many times in a row the same message came up.
+    pub repeats: u32
 }
 
 pub struct GameLog {
-    pub entries: Vec<Item>
+    pub entries: Vec<Item>,
+    // How far through the game events the log has got.
+    pub events: ReaderId<GameEvent>
 }
 
 pub enum Scope {
@@ -19,46 +44,114 @@ pub enum Scope {
 
 pub struct Item {
     pub scope: Scope,
+    pub category: Category,
     pub message: String
 }
 
+impl Category {
+    pub const ALL: [Category; 5] = [Category::Combat, Category::Dance, Category::Noise, Category::Item, Category::System];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Combat => "combat",
+            Category::Dance => "dance",
+            Category::Noise => "noise",
+            Category::Item => "item",
+            Category::System => "system"
+        }
+    }
+
+    pub fn colour(self) -> RGB {
+        match self {
+            Category::Combat => RGB::from_u8(208, 112, 112),
+            Category::Dance => RGB::from_u8(192, 128, 224),
+            Category::Noise => RGB::from_u8(112, 160, 208),
+            Category::Item => RGB::from_u8(192, 176, 96),
+            Category::System => RGB::from_u8(128, 128, 128)
+        }
+    }
+}
+
+impl Entry {
+    pub fn text(&self) -> String {
+        if self.repeats > 1 {
+            format!("{} x{}", self.message, self.repeats)
+        } else {
+            self.message.clone()
+        }
+    }
+}
+
 impl PlayerLog {
     pub fn new() -> Self {
         PlayerLog {
-            entries: Vec::new()
+            entries: Vec::new(),
+            earlier: Vec::new()
+        }
+    }
+
+    /// Adds a message, or counts it again if it's the same as the last one.
+    pub fn insert<S: ToString>(&mut self, turn: Turn, category: Category, message: &S) {
+        let message = message.to_string();
+        if let Some(last) = self.entries.last_mut() {
+            if last.category == category && last.message == message {
+                last.repeats += 1;
+                return;
+            }
         }
+        self.entries.push(Entry { turn, category, message, repeats: 1 });
     }
 
-    pub fn insert<S: ToString>(&mut self, message: &S) {
-        self.entries.push(message.to_string());
+    /// Keeps no more than `max` entries in the log, moving older ones to `earlier`.
+    pub fn trim(&mut self, max: usize) {
+        if self.entries.len() > max {
+            let excess = self.entries.len() - max;
+            self.earlier.extend(self.entries.drain(0..excess));
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.earlier.clear();
+    }
+
+    /// Everything logged this session, oldest first, including what's been trimmed.
+    pub fn all(&self) -> impl Iterator<Item = &Entry> {
+        self.earlier.iter().chain(self.entries.iter())
+    }
+
+    /// Writes everything logged this session to a new text file, giving where it went.
+    pub fn export(&self, seed: Seed) -> io::Result<String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        let path = format!("./log-{}.txt", now);
+        let mut file = fs::File::create(&path)?;
+        writeln!(file, "seed {}", seed)?;
+        for entry in self.all() {
+            writeln!(file, "turn {} [{}] {}", entry.turn, entry.category.name(), entry.text())?;
+        }
+        Ok(path)
     }
 }
 
 impl GameLog {
-    pub fn new() -> Self {
+    pub fn new(events: &mut GameEvents) -> Self {
         GameLog {
-            entries: Vec::new()
+            entries: Vec::new(),
+            events: events.register_reader()
         }
     }
 
-    pub fn global<S: ToString>(&mut self, message: &S) {
+    pub fn global<S: ToString>(&mut self, category: Category, message: &S) {
         self.entries.push(Item {
             scope: Scope::GLOBAL,
+            category,
             message: message.to_string()
         });
     }
 
-    pub fn at<S: ToString>(&mut self, at: Point, message: &S) {
-        self.entries.push(Item {
-            scope: Scope::AT { at: at },
-            message: message.to_string()
-        });
-    }
-
-    pub fn on<S: ToString>(&mut self, on: Entity, message: &S) {
-        self.entries.push(Item {
-            scope: Scope::ON { on: on },
-            message: message.to_string()
-        });
+    /// Forgets everything not yet shown, including events.
+    pub fn clear(&mut self, events: &GameEvents) {
+        self.entries.clear();
+        for _ in events.read(&mut self.events) {}
     }
 }
@@ -1,9 +1,16 @@
 use rltk::{RGB, Rltk, Point, VirtualKeyCode, Rect};
-use super::{Health, Player, gamelog::PlayerLog, Map, Name, state::State, InBackpack, Viewshed, RunState, Equipped, Poise, drawing, dancing, text::capitalize, Stamina, cellinfo::cell_info, CanDoDances, HasAggroedMosters};
+use super::{Health, Player, gamelog::{PlayerLog, Category}, Map, Name, state::{State, Seed, Turn, random_seed}, InBackpack, Viewshed, RunState, Equipped, Poise, drawing, dancing, text::{capitalize, wrap, time_text}, Stamina, cellinfo::cell_info, CanDoDances, HasAggroedMosters, saveload_system::SaveSlot, stats, scores::RunRecord, keymap::{Keymap, key_name}, settings, systems::particle_system::ParticleBuilder};
 use specs::prelude::*;
+use serde::{Serialize, Deserialize};
 
 #[derive(PartialEq, Copy, Clone)]
-pub enum MainMenuSelection { NewGame, LoadGame, Quit }
+pub enum MainMenuSelection { NewGame, LoadGame, HighScores, RunHistory, Options, Quit }
+
+// In the order they're shown.
+const MAIN_MENU: &[MainMenuSelection] = &[
+    MainMenuSelection::LoadGame, MainMenuSelection::NewGame, MainMenuSelection::HighScores, MainMenuSelection::RunHistory,
+    MainMenuSelection::Options, MainMenuSelection::Quit
+];
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum MainMenuResult {
@@ -16,6 +23,42 @@ pub enum ItemMenuResult {
     Cancel, NoResponse, Selected
 }
 
+#[derive(PartialEq, Copy, Clone, Serialize, Deserialize)]
+pub enum MenuInput {
+    Cancel, Select(usize)
+}
+
+/// How the full log screen was left, so it's the same next time it's opened.
+pub struct LogView {
+    pub hidden: Vec<Category>,
+    // Only messages with this in them are shown.
+    pub search: String,
+    // How many entries back from the newest the screen is scrolled.
+    pub scroll: usize,
+    pub input: LogInput,
+    // Shown instead of the keys until the next key press.
+    pub message: Option<String>
+}
+
+#[derive(PartialEq, Clone)]
+pub enum LogInput {
+    Browsing,
+    Searching,
+    JumpingTo(String)
+}
+
+impl LogView {
+    pub fn new() -> Self {
+        LogView {
+            hidden: Vec::new(),
+            search: String::new(),
+            scroll: 0,
+            input: LogInput::Browsing,
+            message: None
+        }
+    }
+}
+
 pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     draw_stats(ecs, ctx);
     draw_log(ecs, ctx);
@@ -69,18 +112,17 @@ fn draw_stat<S: ToString>(name: S, value: i32, max_value: i32, x: i32, y: i32, n
 
 fn draw_log(ecs: &World, ctx: &mut Rltk) {
     let (screen_width, screen_height) = ctx.get_char_size();
-    let fg = RGB::from_u8(128, 128, 128);
     let odd_bg = RGB::from_u8(24, 24, 24);
     let even_bg = RGB::from_u8(16, 16, 16);
     let log = ecs.fetch::<PlayerLog>();
     let avail_y = 7;
     let mut y = screen_height - 1;
-    for (i, msg) in log.entries.iter().enumerate().rev().take(avail_y as usize) {
+    for (i, entry) in log.entries.iter().enumerate().rev().take(avail_y as usize) {
         let bg = if i % 2 == 0 { even_bg } else { odd_bg };
         for x in 0..screen_width {
             ctx.set_bg(x, y, bg);
         }
-        ctx.print_color(0, y, fg, bg, msg);
+        ctx.print_color(0, y, entry.category.colour(), bg, entry.text());
         y -= 1;
     }
 }
@@ -140,38 +182,38 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     }
 }
 
-pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+pub fn show_inventory(gs: &mut State, ctx: &mut Rltk, input: Option<MenuInput>) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
-    inventory_menu::<InBackpack>(gs, ctx, "Use which item?".to_string(), "nothing in inventory".to_string(), &|item: &InBackpack| item.owner == *player_entity)
+    inventory_menu::<InBackpack>(gs, ctx, input, "Use which item?".to_string(), "nothing in inventory".to_string(), &|item: &InBackpack| item.owner == *player_entity)
 }
 
-pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk, input: Option<MenuInput>) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
-    inventory_menu::<InBackpack>(gs, ctx, "Drop which item?".to_string(), "nothing in inventory".to_string(), &|item: &InBackpack| item.owner == *player_entity)
+    inventory_menu::<InBackpack>(gs, ctx, input, "Drop which item?".to_string(), "nothing in inventory".to_string(), &|item: &InBackpack| item.owner == *player_entity)
 }
 
-pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+pub fn remove_item_menu(gs: &mut State, ctx: &mut Rltk, input: Option<MenuInput>) -> (ItemMenuResult, Option<Entity>) {
     let player_entity = gs.ecs.fetch::<Entity>();
-    inventory_menu::<Equipped>(gs, ctx, "Remove which item?".to_string(), "nothing equipped".to_string(), &|item: &Equipped| item.owner == *player_entity)
+    inventory_menu::<Equipped>(gs, ctx, input, "Remove which item?".to_string(), "nothing equipped".to_string(), &|item: &Equipped| item.owner == *player_entity)
 }
 
-pub fn dance_menu(gs: &State, ctx: &mut Rltk) -> (ItemMenuResult, Option<dancing::Dance>) {
+pub fn dance_menu(gs: &State, ctx: &mut Rltk, input: Option<MenuInput>) -> (ItemMenuResult, Option<dancing::Dance>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let can_do_dances = gs.ecs.read_storage::<CanDoDances>();
     let items = 
         if let Some(can_dance) = can_do_dances.get(*player_entity) {
             can_dance.dances.iter().map(|dance| {
-                let name = format!("{} ({} steps)", capitalize(&dance.name()), dance.steps().len());
+                let name = format!("{} ({} steps)", capitalize(&dance.name), dance.steps.len());
                 (name, dance)
             }).collect()
         } else {
             Vec::new()
         };
-    let (result, dance) = menu::<&dancing::Dance>(ctx, "Do which dance?".to_string(), "you can't do any dances".to_string(), items);
+    let (result, dance) = menu::<&dancing::Dance>(ctx, input, "Do which dance?".to_string(), "you can't do any dances".to_string(), items);
     (result, dance.map(|d| d.clone()))
 }
 
-fn inventory_menu<C: Component>(gs: &State, ctx: &mut Rltk, title: String, empty_text: String, filter: &dyn Fn(&C) -> bool) -> (ItemMenuResult, Option<Entity>) {
+fn inventory_menu<C: Component>(gs: &State, ctx: &mut Rltk, input: Option<MenuInput>, title: String, empty_text: String, filter: &dyn Fn(&C) -> bool) -> (ItemMenuResult, Option<Entity>) {
     let entities = gs.ecs.entities();
     let names = gs.ecs.read_storage::<Name>();
     let backpack = gs.ecs.read_storage::<C>();
@@ -182,10 +224,10 @@ fn inventory_menu<C: Component>(gs: &State, ctx: &mut Rltk, title: String, empty
         (n.name.to_string(), e.clone())
     ).collect();
 
-    menu::<Entity>(ctx, title, empty_text, items)
+    menu::<Entity>(ctx, input, title, empty_text, items)
 }
 
-fn menu<T: Clone>(ctx: &mut Rltk, title: String, empty_text: String, items: Vec<(String, T)>) -> (ItemMenuResult, Option<T>) {
+fn menu<T: Clone>(ctx: &mut Rltk, input: Option<MenuInput>, title: String, empty_text: String, items: Vec<(String, T)>) -> (ItemMenuResult, Option<T>) {
     let (screen_width, screen_height) = ctx.get_char_size();
     let count = items.len();
 
@@ -217,24 +259,116 @@ fn menu<T: Clone>(ctx: &mut Rltk, title: String, empty_text: String, items: Vec<
         ctx.print_color(start_x + 2, y, items_fg, bg, empty_text);
     }
 
-    match ctx.key {
+    match input {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(MenuInput::Cancel) => (ItemMenuResult::Cancel, None),
+        Some(MenuInput::Select(selection)) => {
+            if selection < count {
+                return (ItemMenuResult::Selected, Some(items[selection].1.clone()));
+            }
+            (ItemMenuResult::NoResponse, None)
+        }
+    }
+}
+
+pub fn load_game_menu(gs: &State, ctx: &mut Rltk, input: Option<MenuInput>) -> (ItemMenuResult, Option<usize>) {
+    match slot_menu(gs, ctx, input, "Load which game?") {
+        (ItemMenuResult::Selected, Some(slot)) if matches!(gs.save_slots[slot], SaveSlot::Empty) => (ItemMenuResult::NoResponse, None),
+        result => result
+    }
+}
+
+/// Lists the slots to save to, along with why the last save failed if it did.
+pub fn save_game_menu(gs: &State, ctx: &mut Rltk, input: Option<MenuInput>) -> (ItemMenuResult, Option<usize>) {
+    let result = slot_menu(gs, ctx, input, "Save to which slot?");
+    if let Some(message) = &gs.menu_message {
+        let (screen_width, screen_height) = ctx.get_char_size();
+        let y = (screen_height / 2) as i32 + gs.save_slots.len() as i32 + 5;
+        for (i, line) in wrap(message, screen_width as usize - 4).iter().enumerate() {
+            ctx.print_color(2, y + i as i32, RGB::named(rltk::RED), RGB::from_u8(0, 0, 0), line);
+        }
+    }
+    result
+}
+
+fn slot_menu(gs: &State, ctx: &mut Rltk, input: Option<MenuInput>, title: &str) -> (ItemMenuResult, Option<usize>) {
+    let (screen_width, screen_height) = ctx.get_char_size();
+    let count = gs.save_slots.len();
+
+    let bg = RGB::from_u8(64, 64, 64);
+    let title_fg = RGB::from_u8(255, 255, 255);
+    let items_fg = RGB::from_u8(192, 192, 192);
+    let details_fg = RGB::from_u8(128, 128, 128);
+    let key_fg = RGB::from_u8(192, 192, 64);
+
+    let start_x = 2;
+    let start_y = (screen_height / 2) as i32 - count as i32;
+    let width = screen_width as i32 - 4;
+    let height = (count * 2 + 3) as i32;
+
+    ctx.fill_region(Rect::with_size(start_x, start_y, width, height), rltk::to_cp437(' '), items_fg, bg);
+    ctx.print_color(start_x, start_y, title_fg, bg, title);
+    ctx.print_color(start_x, start_y + height, title_fg, bg, "Escape to cancel");
+
+    let mut y = start_y + 2;
+    for (i, slot) in gs.save_slots.iter().enumerate() {
+        ctx.set(start_x, y, key_fg, bg, 97 + i as rltk::FontCharType);
+        match slot {
+            SaveSlot::Empty => {
+                ctx.print_color(start_x + 2, y, items_fg, bg, format!("Slot {}: empty", i + 1));
+            }
+            SaveSlot::Saved(None) => {
+                ctx.print_color(start_x + 2, y, items_fg, bg, format!("Slot {}: saved game", i + 1));
+                ctx.print_color(start_x + 4, y + 1, details_fg, bg, "(from an older version, no details)");
+            }
+            SaveSlot::Saved(Some(summary)) => {
+                ctx.print_color(start_x + 2, y, items_fg, bg, format!("Slot {}: floor {}, turn {}, {} {} / {}, {} {} / {}",
+                    i + 1, summary.depth, summary.turn,
+                    Health::NAME, summary.health.0, summary.health.1,
+                    Poise::NAME, summary.poise.0, summary.poise.1));
+                ctx.print_color(start_x + 4, y + 1, details_fg, bg, format!("seed {}, saved {}", summary.seed, summary.saved_at_text()));
+            }
+        }
+        y += 2;
+    }
+
+    match input {
         None => (ItemMenuResult::NoResponse, None),
+        Some(MenuInput::Cancel) => (ItemMenuResult::Cancel, None),
+        Some(MenuInput::Select(selection)) => {
+            if selection < count {
+                return (ItemMenuResult::Selected, Some(selection));
+            }
+            (ItemMenuResult::NoResponse, None)
+        }
+    }
+}
+
+pub fn menu_input(ctx: &Rltk) -> Option<MenuInput> {
+    match ctx.key {
+        None => None,
+        Some(VirtualKeyCode::Escape) => Some(MenuInput::Cancel),
         Some(key) => {
-            match key {
-                VirtualKeyCode::Escape => { (ItemMenuResult::Cancel, None) }
-                _ => {
-                    let selection = rltk::letter_to_option(key);
-                    if selection > -1 && selection < count as i32 {
-                        return (ItemMenuResult::Selected, Some(items[selection as usize].1.clone()));
-                    }
-                    (ItemMenuResult::NoResponse, None)
-                }
+            let selection = rltk::letter_to_option(key);
+            if selection > -1 {
+                Some(MenuInput::Select(selection as usize))
+            } else {
+                None
             }
         }
     }
 }
 
-pub fn ranged_target(gs: &mut State, ctx: &mut Rltk, range: i32) -> (ItemMenuResult, Option<Point>) {
+pub fn target_click(gs: &State, ctx: &mut Rltk) -> Option<Point> {
+    if ctx.left_click {
+        let (mouse_x, mouse_y) = ctx.mouse_pos();
+        Some(drawing::screen_to_world_point(Point::new(mouse_x, mouse_y), &gs.ecs, ctx))
+    } else {
+        None
+    }
+}
+
+pub fn ranged_target(gs: &mut State, ctx: &mut Rltk, range: i32, click: Option<Point>) -> (ItemMenuResult, Option<Point>) {
     let player_entity = gs.ecs.fetch::<Entity>();
     let player_pos = gs.ecs.fetch::<Point>();
     let viewsheds = gs.ecs.read_storage::<Viewshed>();
@@ -265,32 +399,28 @@ pub fn ranged_target(gs: &mut State, ctx: &mut Rltk, range: i32) -> (ItemMenuRes
 
     let (mouse_x, mouse_y) = ctx.mouse_pos();
     let world_mouse_pos = drawing::screen_to_world_point(Point::new(mouse_x, mouse_y), &gs.ecs, ctx);
-    let mut valid_target = false;
-    for tile in available_world_cells.iter() {
-        if tile.x == world_mouse_pos.x && tile.y == world_mouse_pos.y {
-            valid_target = true;
-            break;
-        }
-    }
-    if valid_target {
+    if available_world_cells.contains(&world_mouse_pos) {
         ctx.set_bg(mouse_x, mouse_y, target_valid_bg);
-        if ctx.left_click {
-            return (ItemMenuResult::Selected, Some(world_mouse_pos));
-        }
     } else {
         ctx.set_bg(mouse_x, mouse_y, target_invalid_bg);
-        if ctx.left_click {
-            return (ItemMenuResult::Cancel, None);
-        }
     }
 
-    (ItemMenuResult::NoResponse, None)
+    match click {
+        None => (ItemMenuResult::NoResponse, None),
+        Some(target) => {
+            if available_world_cells.contains(&target) {
+                (ItemMenuResult::Selected, Some(target))
+            } else {
+                (ItemMenuResult::Cancel, None)
+            }
+        }
+    }
 }
 
 pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
-    let (_, screen_height) = ctx.get_char_size();
+    let (screen_width, screen_height) = ctx.get_char_size();
 
-    let save_exists = super::saveload_system::does_save_exist();
+    let save_exists = gs.save_slots.iter().any(|slot| matches!(slot, SaveSlot::Saved(_)));
     let runstate = gs.ecs.fetch::<RunState>();
 
     let title_bg = RGB::from_u8(0, 0, 0);
@@ -299,7 +429,10 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     let title_fg = RGB::from_u8(255, 255, 255);
     let continue_game_fg = Health::colour();
     let new_game_fg = Stamina::colour();
+    let scores_fg = RGB::from_u8(192, 192, 192);
     let quit_fg = Poise::colour();
+    let seed_help_fg = RGB::from_u8(128, 128, 128);
+    let message_fg = RGB::named(rltk::RED);
 
     let title_y = (screen_height / 4) as i32;
     let items_y = (screen_height / 2 - 2) as i32;
@@ -321,7 +454,7 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                 fg = fg.to_greyscale();
             }
             let bg = if selection == MainMenuSelection::LoadGame { sel_bg } else { unsel_bg };
-            ctx.print_color_centered(y, fg, bg, "Continue Game");
+            ctx.print_color_centered(y, fg, bg, "Load Game");
             y += 1;
         }
 
@@ -329,8 +462,33 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
         ctx.print_color_centered(y, new_game_fg, bg, "New Game");
         y += 1;
 
+        let bg = if selection == MainMenuSelection::HighScores { sel_bg } else { unsel_bg };
+        ctx.print_color_centered(y, scores_fg, bg, "High Scores");
+        y += 1;
+
+        let bg = if selection == MainMenuSelection::RunHistory { sel_bg } else { unsel_bg };
+        ctx.print_color_centered(y, scores_fg, bg, "Run History");
+        y += 1;
+
+        let bg = if selection == MainMenuSelection::Options { sel_bg } else { unsel_bg };
+        ctx.print_color_centered(y, scores_fg, bg, "Options");
+        y += 1;
+
         let bg = if selection == MainMenuSelection::Quit { sel_bg } else { unsel_bg };
         ctx.print_color_centered(y, quit_fg, bg, "Quit");
+        y += 2;
+
+        ctx.print_color_centered(y, title_fg, title_bg, format!("Seed for new game: {}", gs.new_game_seed));
+        y += 1;
+        ctx.print_color_centered(y, seed_help_fg, title_bg, "(type digits or backspace to change, r for random)");
+
+        if let Some(message) = &gs.menu_message {
+            y += 1;
+            for line in wrap(message, screen_width as usize - 4) {
+                y += 1;
+                ctx.print_color_centered(y, message_fg, title_bg, line);
+            }
+        }
 
         match ctx.key {
             None => return MainMenuResult::NoSelection{ selected: selection },
@@ -338,25 +496,30 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
                 match key {
                     VirtualKeyCode::Escape => { return MainMenuResult::NoSelection{ selected: MainMenuSelection::Quit } }
                     VirtualKeyCode::Up | VirtualKeyCode::K => {
-                        let mut newselection = selection;
-                        match selection {
-                            MainMenuSelection::LoadGame => {},
-                            MainMenuSelection::NewGame => newselection = MainMenuSelection::LoadGame,
-                            MainMenuSelection::Quit => newselection = MainMenuSelection::NewGame
-                        }
-                        return MainMenuResult::NoSelection{ selected: newselection }
+                        let i = MAIN_MENU.iter().position(|s| *s == selection).unwrap_or(0);
+                        return MainMenuResult::NoSelection{ selected: MAIN_MENU[i.saturating_sub(1)] }
                     }
                     VirtualKeyCode::Down | VirtualKeyCode::J => {
-                        let mut newselection = selection;
-                        match selection {
-                            MainMenuSelection::LoadGame => newselection = MainMenuSelection::NewGame,
-                            MainMenuSelection::NewGame => newselection = MainMenuSelection::Quit,
-                            MainMenuSelection::Quit => {}
-                        }
-                        return MainMenuResult::NoSelection{ selected: newselection }
+                        let i = MAIN_MENU.iter().position(|s| *s == selection).unwrap_or(0);
+                        return MainMenuResult::NoSelection{ selected: MAIN_MENU[usize::min(i + 1, MAIN_MENU.len() - 1)] }
                     }
                     VirtualKeyCode::Return => { return MainMenuResult::Selected{ selected: selection } }
-                    _ => return MainMenuResult::NoSelection{ selected: selection }
+                    VirtualKeyCode::Back => {
+                        gs.new_game_seed /= 10;
+                        return MainMenuResult::NoSelection{ selected: selection }
+                    }
+                    VirtualKeyCode::R => {
+                        gs.new_game_seed = random_seed();
+                        return MainMenuResult::NoSelection{ selected: selection }
+                    }
+                    _ => {
+                        if let Some(digit) = key_digit(key) {
+                            if let Some(seed) = gs.new_game_seed.checked_mul(10).and_then(|s| s.checked_add(digit)) {
+                                gs.new_game_seed = seed;
+                            }
+                        }
+                        return MainMenuResult::NoSelection{ selected: selection }
+                    }
                 }
             }
         }
@@ -365,11 +528,98 @@ pub fn main_menu(gs: &mut State, ctx: &mut Rltk) -> MainMenuResult {
     MainMenuResult::NoSelection { selected: MainMenuSelection::NewGame }
 }
 
+fn key_digit(key: VirtualKeyCode) -> Option<Seed> {
+    match key {
+        VirtualKeyCode::Key0 | VirtualKeyCode::Numpad0 => Some(0),
+        VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Some(1),
+        VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Some(2),
+        VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Some(3),
+        VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Some(4),
+        VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Some(5),
+        VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Some(6),
+        VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Some(7),
+        VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Some(8),
+        VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Some(9),
+        _ => None
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum OptionsMenuResult {
+    NoSelection{ selected: usize },
+    Close
+}
+
+/// Shows the settings, changing them as they're picked and saving each change straight away.
+pub fn options_menu(gs: &mut State, ctx: &mut Rltk, selection: usize) -> OptionsMenuResult {
+    let (screen_width, screen_height) = ctx.get_char_size();
+
+    let bg = RGB::from_u8(0, 0, 0);
+    let sel_bg = RGB::from_u8(64, 64, 64);
+    let title_fg = RGB::from_u8(255, 255, 255);
+    let name_fg = RGB::from_u8(192, 192, 192);
+    let value_fg = Stamina::colour();
+    let help_fg = RGB::from_u8(128, 128, 128);
+    let message_fg = RGB::named(rltk::RED);
+
+    let x = 2;
+    ctx.print_color(x, 1, title_fg, bg, "Options");
+    ctx.print_color(x, screen_height as i32 - 2, title_fg, bg, "Up and down to pick, left, right or return to change, escape to return");
+
+    let mut y = 3;
+    for (i, setting) in settings::SETTINGS.iter().enumerate() {
+        let row_bg = if i == selection { sel_bg } else { bg };
+        ctx.print_color(x, y, name_fg, row_bg, format!("{:<33}", setting.name()));
+        ctx.print_color(x + 33, y, value_fg, row_bg, format!("{:<10}", gs.settings.value_text(*setting)));
+        if let Some(note) = setting.note() {
+            ctx.print_color(x + 44, y, help_fg, bg, format!("({})", note));
+        }
+        y += 1;
+    }
+
+    if let Some(message) = &gs.menu_message {
+        y += 1;
+        for line in wrap(message, screen_width as usize - 4) {
+            y += 1;
+            ctx.print_color(x, y, message_fg, bg, line);
+        }
+    }
+
+    let forward = match ctx.key {
+        None => return OptionsMenuResult::NoSelection{ selected: selection },
+        Some(VirtualKeyCode::Escape) => return OptionsMenuResult::Close,
+        Some(VirtualKeyCode::Up) | Some(VirtualKeyCode::K) => {
+            return OptionsMenuResult::NoSelection{ selected: selection.saturating_sub(1) }
+        }
+        Some(VirtualKeyCode::Down) | Some(VirtualKeyCode::J) => {
+            return OptionsMenuResult::NoSelection{ selected: usize::min(selection + 1, settings::SETTINGS.len() - 1) }
+        }
+        Some(VirtualKeyCode::Left) | Some(VirtualKeyCode::H) => false,
+        Some(VirtualKeyCode::Right) | Some(VirtualKeyCode::L) | Some(VirtualKeyCode::Return) => true,
+        Some(_) => return OptionsMenuResult::NoSelection{ selected: selection }
+    };
+
+    gs.settings.change(settings::SETTINGS[selection], forward);
+    gs.ecs.fetch_mut::<ParticleBuilder>().set_lifetime_percent(gs.settings.particle_time);
+    if let Err(e) = gs.settings.save() {
+        gs.menu_message = Some(format!("Couldn't save the settings: {}.", e));
+    }
+    OptionsMenuResult::NoSelection{ selected: selection }
+}
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum GameOverResult { NoSelection, QuitToMenu }
 
-pub fn game_over(won: bool, message: &String, ctx: &mut Rltk) -> GameOverResult {
+pub fn game_over(won: bool, message: &String, gs: &State, ctx: &mut Rltk) -> GameOverResult {
     let (screen_width, screen_height) = ctx.get_char_size();
+    let seed = *gs.ecs.fetch::<Seed>();
+    let turn = *gs.ecs.fetch::<Turn>();
+    let mut lines = gs.ecs.fetch::<stats::Stats>().run.lines(turn);
+    lines.push(String::new());
+    lines.push(format!("Seed: {}", seed));
+    let score = RunRecord::new(seed, won, message, gs.ecs.fetch::<Map>().depth, turn, &gs.ecs.fetch::<stats::Stats>().run).score();
+    lines.insert(0, format!("Score: {}", score));
+    lines.extend(gs.game_over_messages.iter().cloned());
 
     let title_fg = RGB::from_u8(255, 255, 255);
     let message_fg = RGB::from_u8(192, 192, 192);
@@ -379,11 +629,15 @@ pub fn game_over(won: bool, message: &String, ctx: &mut Rltk) -> GameOverResult
     let won_lost_msg = if won { "won" } else { "lost" };
     let bg = if won { won_bg } else { lost_bg };
 
-    let y = (screen_height - 8) / 2 - 3;
-    ctx.fill_region(Rect::with_size(0, y - 1, screen_width, 6), rltk::to_cp437(' '), title_fg, bg);
+    let height = lines.len() as u32 + 8;
+    let y = (screen_height - height) / 2 - 3;
+    ctx.fill_region(Rect::with_size(0, y - 1, screen_width, height), rltk::to_cp437(' '), title_fg, bg);
     ctx.print_color_centered(y, title_fg, bg, format!("You {}!", won_lost_msg));
     ctx.print_color_centered(y + 2, message_fg, bg, message);
-    ctx.print_color_centered(y + 4, title_fg, bg, "Press escape to return to the menu.");
+    for (i, line) in lines.iter().enumerate() {
+        ctx.print_color_centered(y + 4 + i as u32, message_fg, bg, line);
+    }
+    ctx.print_color_centered(y + 5 + lines.len() as u32, title_fg, bg, "Press escape to return to the menu.");
 
     match ctx.key {
         Some(VirtualKeyCode::Escape) => GameOverResult::QuitToMenu,
@@ -391,66 +645,227 @@ pub fn game_over(won: bool, message: &String, ctx: &mut Rltk) -> GameOverResult
     }
 }
 
+/// The best games so far, best first.
+pub fn high_scores(gs: &State, ctx: &mut Rltk) -> ItemMenuResult {
+    let runs = gs.scores.as_ref().map(|scores| scores.high_scores());
+    run_table(ctx, "High Scores", runs, true)
+}
+
+/// The games played, latest first.
+pub fn run_history(gs: &State, ctx: &mut Rltk) -> ItemMenuResult {
+    let runs = gs.scores.as_ref().map(|scores| scores.history().collect());
+    run_table(ctx, "Run History", runs, false)
+}
+
+// Lists as many runs as fit, picking out the ones that were won.
+fn run_table(ctx: &mut Rltk, title: &str, runs: Result<Vec<&RunRecord>, &String>, ranked: bool) -> ItemMenuResult {
+    let (_, screen_height) = ctx.get_char_size();
+
+    let bg = RGB::from_u8(0, 0, 0);
+    let title_fg = RGB::from_u8(255, 255, 255);
+    let heading_fg = RGB::from_u8(128, 128, 128);
+    let lost_fg = RGB::from_u8(192, 192, 192);
+    let won_fg = RGB::named(rltk::GOLD);
+    let won_bg = RGB::from_u8(48, 48, 16);
+    let message_fg = RGB::named(rltk::RED);
+
+    let x = 2;
+    ctx.print_color(x, 1, title_fg, bg, title);
+    ctx.print_color(x, screen_height as i32 - 2, title_fg, bg, "Escape to return");
+
+    match runs {
+        Err(e) => {
+            ctx.print_color(x, 3, message_fg, bg, format!("Can't show the runs, since {}.", e));
+        }
+        Ok(runs) if runs.is_empty() => {
+            ctx.print_color(x, 3, lost_fg, bg, "No games finished yet.");
+        }
+        Ok(runs) => {
+            ctx.print_color(x, 3, heading_fg, bg, format!("{:>3} {:>6} {:<18} {:>5} {:>5} {:>5} {:>5} {}",
+                "", "score", "", "floor", "turns", "kills", "awe", "ended"));
+            let shown = screen_height as usize - 8;
+            for (i, run) in runs.iter().take(shown).enumerate() {
+                let rank = if ranked { format!("{}.", i + 1) } else { String::new() };
+                let (fg, row_bg) = if run.won { (won_fg, won_bg) } else { (lost_fg, bg) };
+                ctx.print_color(x, 5 + i as i32, fg, row_bg, format!("{:>3} {:>6} {:<18} {:>5} {:>5} {:>5} {:>5} {}",
+                    rank, run.score(), run.reason, run.depth, run.turn, run.stats.monsters_killed, run.stats.poise_inflicted,
+                    time_text(run.ended_at)));
+            }
+        }
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => ItemMenuResult::Cancel,
+        _ => ItemMenuResult::NoResponse
+    }
+}
+
 pub fn show_full_log(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<()>) {
     let (screen_width, screen_height) = ctx.get_char_size();
 
     let title_fg = RGB::from_u8(255, 255, 255);
     let title_bg = RGB::from_u8(0, 0, 0);
     let bg = RGB::from_u8(0, 0, 0);
-    let fg = RGB::from_u8(128, 128, 128);
+    let hidden_fg = RGB::from_u8(64, 64, 64);
+    let turn_fg = RGB::from_u8(96, 96, 96);
     let odd_bg = RGB::from_u8(24, 24, 24);
     let even_bg = RGB::from_u8(16, 16, 16);
 
     let log = gs.ecs.fetch::<PlayerLog>();
+    let view = &mut gs.log_view;
 
     ctx.fill_region(Rect::with_size(0, 0, screen_width, screen_height), rltk::to_cp437(' '), title_fg, bg);
     ctx.print_color(0, 0, title_fg, title_bg, "Log");
-    ctx.print_color(0, screen_height - 1, title_fg, title_bg, "Escape to return to game");
+    let mut x = 5;
+    for (i, category) in Category::ALL.iter().enumerate() {
+        let fg = if view.hidden.contains(category) { hidden_fg } else { category.colour() };
+        let label = format!("{} {}", i + 1, category.name());
+        ctx.print_color(x, 0, fg, title_bg, &label);
+        x += label.len() as i32 + 2;
+    }
+    if !view.search.is_empty() {
+        ctx.print_color(x, 0, title_fg, title_bg, format!("/{}", view.search));
+    }
 
-    let avail_y = screen_height - 2;
-    let end_i = log.entries.len() as i32;
-    let start_i = i32::max(0, end_i - avail_y as i32);
+    let search = view.search.to_lowercase();
+    // Trimmed entries are shown too, so searching and jumping find everything that was exported.
+    let shown: Vec<_> = log.all()
+        .filter(|entry| !view.hidden.contains(&entry.category))
+        .filter(|entry| search.is_empty() || entry.message.to_lowercase().contains(&search))
+        .collect();
+    let avail_y = (screen_height - 2) as usize;
+    view.scroll = usize::min(view.scroll, shown.len().saturating_sub(avail_y));
+    let end_i = shown.len() - view.scroll;
+    let start_i = end_i.saturating_sub(avail_y);
 
     let mut y = 1;
-    for i in start_i..end_i {
-        let msg = &log.entries[i as usize];
+    for (i, entry) in shown.iter().enumerate().take(end_i).skip(start_i) {
         let bg = if i % 2 == 0 { even_bg } else { odd_bg };
         for x in 0..screen_width {
             ctx.set_bg(x, y, bg);
         }
-        ctx.print_color(0, y, fg, bg, msg);
+        ctx.print_color(0, y, turn_fg, bg, format!("{:>6}", entry.turn));
+        ctx.print_color(7, y, entry.category.colour(), bg, entry.text());
         y += 1;
     }
 
-    match ctx.key {
-        None => (ItemMenuResult::NoResponse, None),
-        Some(key) => {
+    let footer = match &view.input {
+        LogInput::Searching => format!("Search: {}_", view.search),
+        LogInput::JumpingTo(turn) => format!("Jump to turn: {}_", turn),
+        LogInput::Browsing => view.message.clone().unwrap_or_else(|| {
+            "1-5 show or hide, / search, t jump to turn, x export, arrows scroll, Escape return".to_string()
+        })
+    };
+    ctx.print_color(0, screen_height - 1, title_fg, title_bg, footer);
+
+    let key = match ctx.key {
+        None => return (ItemMenuResult::NoResponse, None),
+        Some(key) => key
+    };
+    view.message = None;
+    match view.input.clone() {
+        LogInput::Searching => {
             match key {
-                VirtualKeyCode::Escape => { (ItemMenuResult::Cancel, None) }
-                _ => (ItemMenuResult::NoResponse, None)
+                VirtualKeyCode::Escape => {
+                    view.search.clear();
+                    view.input = LogInput::Browsing;
+                }
+                VirtualKeyCode::Return => view.input = LogInput::Browsing,
+                VirtualKeyCode::Back => { view.search.pop(); }
+                _ => {
+                    if let Some(c) = key_char(key, ctx.shift) {
+                        view.search.push(c);
+                        view.scroll = 0;
+                    }
+                }
+            }
+        }
+        LogInput::JumpingTo(mut turn) => {
+            match key {
+                VirtualKeyCode::Escape => view.input = LogInput::Browsing,
+                VirtualKeyCode::Return => {
+                    // Scroll so the first entry from that turn on is at the top.
+                    if let Ok(turn) = turn.parse::<Turn>() {
+                        let first = shown.iter().position(|entry| entry.turn >= turn).unwrap_or(shown.len());
+                        view.scroll = shown.len() - usize::min(shown.len(), first + avail_y);
+                    }
+                    view.input = LogInput::Browsing;
+                }
+                VirtualKeyCode::Back => {
+                    turn.pop();
+                    view.input = LogInput::JumpingTo(turn);
+                }
+                _ => {
+                    if let Some(digit) = key_digit(key) {
+                        turn.push_str(&digit.to_string());
+                        view.input = LogInput::JumpingTo(turn);
+                    }
+                }
+            }
+        }
+        LogInput::Browsing => {
+            match key {
+                VirtualKeyCode::Escape => return (ItemMenuResult::Cancel, None),
+                VirtualKeyCode::Slash => view.input = LogInput::Searching,
+                VirtualKeyCode::T => view.input = LogInput::JumpingTo(String::new()),
+                VirtualKeyCode::X => {
+                    let seed = *gs.ecs.fetch::<Seed>();
+                    view.message = Some(match log.export(seed) {
+                        Ok(path) => format!("Log written to {}", path),
+                        Err(e) => format!("Couldn't write the log: {}", e)
+                    });
+                }
+                VirtualKeyCode::Up => view.scroll += 1,
+                VirtualKeyCode::Down => view.scroll = view.scroll.saturating_sub(1),
+                VirtualKeyCode::PageUp => view.scroll += avail_y,
+                VirtualKeyCode::PageDown => view.scroll = view.scroll.saturating_sub(avail_y),
+                VirtualKeyCode::Home => view.scroll = shown.len(),
+                VirtualKeyCode::End => view.scroll = 0,
+                _ => {
+                    let category = key_digit(key).filter(|&d| d >= 1).and_then(|d| Category::ALL.get(d as usize - 1));
+                    if let Some(category) = category {
+                        if view.hidden.contains(category) {
+                            view.hidden.retain(|c| c != category);
+                        } else {
+                            view.hidden.push(*category);
+                        }
+                    }
+                }
             }
         }
     }
+    (ItemMenuResult::NoResponse, None)
 }
 
-pub fn show_keys(ctx: &mut Rltk) -> (ItemMenuResult, Option<()>) {
-    let keys = vec![
-        ("escape", "return to main menu"),
-        ("mouse", "look around"),
-        ("mouse", "target ranged item (on use)"),
-        ("arrows", "move"),
-        ("hjklyubn", "move"),
-        ("a + movement", "auto move"),
-        (".", "skip a turn"),
-        ("g", "get item"),
-        ("i", "see inventory and use item"),
-        ("d", "drop item"),
-        ("r", "unequip item"),
-        ("z", "do dance"),
-        ("space", "go down stairs"),
-        ("m", "show message log"),
-        ("/", "show this help"),
+// The character a key types, for the few places that take text.
+pub fn key_char(key: VirtualKeyCode, shift: bool) -> Option<char> {
+    let c = match key {
+        VirtualKeyCode::A => 'a', VirtualKeyCode::B => 'b', VirtualKeyCode::C => 'c', VirtualKeyCode::D => 'd',
+        VirtualKeyCode::E => 'e', VirtualKeyCode::F => 'f', VirtualKeyCode::G => 'g', VirtualKeyCode::H => 'h',
+        VirtualKeyCode::I => 'i', VirtualKeyCode::J => 'j', VirtualKeyCode::K => 'k', VirtualKeyCode::L => 'l',
+        VirtualKeyCode::M => 'm', VirtualKeyCode::N => 'n', VirtualKeyCode::O => 'o', VirtualKeyCode::P => 'p',
+        VirtualKeyCode::Q => 'q', VirtualKeyCode::R => 'r', VirtualKeyCode::S => 's', VirtualKeyCode::T => 't',
+        VirtualKeyCode::U => 'u', VirtualKeyCode::V => 'v', VirtualKeyCode::W => 'w', VirtualKeyCode::X => 'x',
+        VirtualKeyCode::Y => 'y', VirtualKeyCode::Z => 'z',
+        VirtualKeyCode::Space => ' ',
+        VirtualKeyCode::Apostrophe => '\'',
+        VirtualKeyCode::Minus => '-',
+        VirtualKeyCode::Period => '.',
+        VirtualKeyCode::Comma => ',',
+        _ => return key_digit(key).and_then(|d| std::char::from_digit(d as u32, 10))
+    };
+    Some(if shift { c.to_ascii_uppercase() } else { c })
+}
+
+pub fn show_keys(keymap: &Keymap, ctx: &mut Rltk) -> (ItemMenuResult, Option<()>) {
+    let mut keys = vec![
+        ("mouse".to_string(), "look around".to_string()),
+        ("mouse".to_string(), "target ranged item (on use)".to_string())
     ];
+    for binding in keymap.bindings.iter().filter(|binding| !binding.keys.is_empty()) {
+        let names: Vec<String> = binding.keys.iter().map(|key| key_name(*key)).collect();
+        keys.push((names.join(" "), binding.help.clone()));
+    }
     show_pairs(ctx, "Help".to_string(), &keys)
 }
 
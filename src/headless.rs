@@ -0,0 +1,398 @@
+use std::fmt;
+use std::fs;
+use specs::prelude::*;
+use rltk::{Point};
+use crate::{state::{State, Turn, Seed}, RunState, Map, Name, Position, Health, Stamina, Poise, Monster, InBackpack, Ranged, CanDoDances, Dancing, KeyState, WantsToUseItem, WantsToDropItem, WantsToDance, gamelog::PlayerLog, player, systems::{particle_system, auto_movement_system}};
+
+// Particles only matter for drawing, so each tick is treated as long enough for all of them to expire.
+const FRAME_TIME_MS: f32 = 1000.0;
+const MAX_TICKS_PER_ACTION: u32 = 1000;
+
+/// Drives the game without an Rltk context, for scripted runs and tests.
+pub struct Simulation {
+    pub state: State
+}
+
+#[derive(Clone)]
+pub struct ActorReport {
+    pub name: String,
+    pub position: Point,
+    pub health: Option<(i32, i32)>,
+    pub stamina: Option<(i32, i32)>,
+    pub poise: Option<(i32, i32)>,
+    pub dancing: Option<String>
+}
+
+#[derive(Clone)]
+pub struct WorldReport {
+    pub seed: Seed,
+    pub turn: Turn,
+    pub depth: i32,
+    pub outcome: Option<(bool, String)>,
+    pub player: Option<ActorReport>,
+    pub monsters: Vec<ActorReport>,
+    pub inventory: Vec<String>,
+    pub log: Vec<String>
+}
+
+pub enum Command {
+    Move { dx: i32, dy: i32 },
+    AutoMove { dx: i32, dy: i32 },
+    Rest { turns: u32 },
+    PickUp,
+    Use { item: String, target: Option<Point> },
+    Drop { item: String },
+    Dance { dance: String },
+    Descend,
+    Report
+}
+
+impl Simulation {
+    pub fn new(seed: Seed) -> Result<Self, String> {
+        let mut state = State::new();
+        state.setup_resources();
+        state.load_data()?;
+        state.ecs.insert(RunState::PreRun);
+        state.setup_world(seed, auto_movement_system::AutoMoveRules::default());
+        let mut sim = Simulation { state };
+        sim.run_until_input();
+        Ok(sim)
+    }
+
+    pub fn player(&self) -> Entity {
+        *self.state.ecs.fetch::<Entity>()
+    }
+
+    pub fn runstate(&self) -> RunState {
+        (*self.state.ecs.fetch::<RunState>()).clone()
+    }
+
+    pub fn is_over(&self) -> bool {
+        matches!(self.runstate(), RunState::GameOver { .. })
+    }
+
+    /// Gives the player an intent component, to be acted on by the next turn.
+    pub fn intend<C: Component>(&mut self, intent: C) {
+        let player = self.player();
+        self.state.ecs.write_storage::<C>().insert(player, intent).expect("Unable to insert intent");
+    }
+
+    /// Starts from the given run state and ticks until the player is needed again.
+    pub fn act(&mut self, runstate: RunState) {
+        if self.is_over() {
+            return;
+        }
+        *self.state.ecs.write_resource::<RunState>() = runstate;
+        self.run_until_input();
+    }
+
+    /// Carries out a command, giving back the state of the world if it asked for a report.
+    pub fn command(&mut self, command: &Command) -> Result<Option<WorldReport>, String> {
+        match command {
+            Command::Move { dx, dy } => {
+                player::try_move_player(*dx, *dy, &mut self.state.ecs);
+                self.act(RunState::Ticking);
+            }
+            Command::AutoMove { dx, dy } => {
+                self.state.ecs.fetch_mut::<KeyState>().requested_auto_move = true;
+                player::try_move_player(*dx, *dy, &mut self.state.ecs);
+                self.act(RunState::Ticking);
+            }
+            Command::Rest { turns } => {
+                for _ in 0..*turns {
+                    let runstate = player::skip_turn(&mut self.state.ecs);
+                    self.act(runstate);
+                }
+            }
+            Command::PickUp => {
+                player::get_item(&mut self.state.ecs);
+                self.act(RunState::Ticking);
+            }
+            Command::Use { item, target } => {
+                let item = self.find_carried(item)?;
+                if player::player_can_act(&mut self.state.ecs) {
+                    if self.state.ecs.read_storage::<Ranged>().get(item).is_some() && target.is_none() {
+                        return Err("item needs a target".to_string());
+                    }
+                    self.intend(WantsToUseItem { item, target: *target });
+                }
+                self.act(RunState::Ticking);
+            }
+            Command::Drop { item } => {
+                let item = self.find_carried(item)?;
+                if player::player_can_act(&mut self.state.ecs) {
+                    self.intend(WantsToDropItem { item });
+                }
+                self.act(RunState::Ticking);
+            }
+            Command::Dance { dance } => {
+                let dance = {
+                    let can_do_dances = self.state.ecs.read_storage::<CanDoDances>();
+                    can_do_dances.get(self.player())
+                        .and_then(|can| can.dances.iter().find(|d| d.name == *dance).cloned())
+                        .ok_or(format!("player can't do the {} dance", dance))?
+                };
+                if player::player_can_act(&mut self.state.ecs) {
+                    self.intend(WantsToDance { dance, repetitions: 1 });
+                }
+                self.act(RunState::Ticking);
+            }
+            Command::Descend => {
+                if player::try_next_level(&mut self.state.ecs) {
+                    self.act(RunState::NextLevel);
+                } else {
+                    self.act(RunState::Ticking);
+                }
+            }
+            Command::Report => return Ok(Some(self.report()))
+        }
+        Ok(None)
+    }
+
+    pub fn report(&self) -> WorldReport {
+        let ecs = &self.state.ecs;
+        let player = self.player();
+        let entities = ecs.entities();
+        let monsters = ecs.read_storage::<Monster>();
+        let names = ecs.read_storage::<Name>();
+        let backpack = ecs.read_storage::<InBackpack>();
+
+        let outcome =
+            if let RunState::GameOver { won, reason } = self.runstate() {
+                Some((won, reason))
+            } else {
+                None
+            };
+
+        WorldReport {
+            seed: *ecs.fetch::<Seed>(),
+            turn: *ecs.fetch::<Turn>(),
+            depth: ecs.fetch::<Map>().depth,
+            outcome,
+            player: actor_report(ecs, player),
+            monsters: (&entities, &monsters).join().filter_map(|(e, _)| actor_report(ecs, e)).collect(),
+            inventory: (&backpack, &names).join().filter(|(bp, _)| bp.owner == player).map(|(_, n)| n.name.to_string()).collect(),
+            log: ecs.fetch::<PlayerLog>().entries.iter().map(|entry| entry.text()).collect()
+        }
+    }
+
+    fn run_until_input(&mut self) {
+        for _ in 0..MAX_TICKS_PER_ACTION {
+            if self.wants_input() {
+                return;
+            }
+            self.tick();
+        }
+    }
+
+    fn wants_input(&self) -> bool {
+        match self.runstate() {
+            RunState::PreRun | RunState::Ticking | RunState::NextLevel => false,
+            RunState::AwaitingInput => !self.player_is_busy(),
+            _ => true
+        }
+    }
+
+    fn player_is_busy(&self) -> bool {
+        self.state.player_is_busy() || auto_movement_system::is_auto_moving(&self.state.ecs, self.player())
+    }
+
+    fn tick(&mut self) {
+        particle_system::cull_dead_particles(&mut self.state.ecs, FRAME_TIME_MS);
+        let runstate = self.runstate();
+        let newrunstate =
+            if runstate == RunState::AwaitingInput {
+                RunState::Ticking
+            } else {
+                self.state.advance(&runstate)
+            };
+        self.state.finish_tick(newrunstate);
+    }
+
+    fn find_carried(&self, item_name: &str) -> Result<Entity, String> {
+        let player = self.player();
+        let entities = self.state.ecs.entities();
+        let names = self.state.ecs.read_storage::<Name>();
+        let backpack = self.state.ecs.read_storage::<InBackpack>();
+        (&entities, &backpack, &names).join()
+            .find(|(_, bp, n)| bp.owner == player && n.name == item_name)
+            .map(|(e, _, _)| e)
+            .ok_or(format!("player isn't carrying {}", item_name))
+    }
+}
+
+fn actor_report(ecs: &World, entity: Entity) -> Option<ActorReport> {
+    let names = ecs.read_storage::<Name>();
+    let positions = ecs.read_storage::<Position>();
+    let health = ecs.read_storage::<Health>();
+    let stamina = ecs.read_storage::<Stamina>();
+    let poise = ecs.read_storage::<Poise>();
+    let dancing = ecs.read_storage::<Dancing>();
+
+    let pos = positions.get(entity)?;
+    Some(ActorReport {
+        name: names.get(entity).map_or("?".to_string(), |n| n.name.to_string()),
+        position: Point::new(pos.x, pos.y),
+        health: health.get(entity).map(|h| (h.health, h.max_health)),
+        stamina: stamina.get(entity).map(|s| (s.stamina, s.max_stamina)),
+        poise: poise.get(entity).map(|p| (p.poise, p.max_poise)),
+        dancing: dancing.get(entity).map(|d| d.dance.name.clone())
+    })
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Option<Command>, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let command =
+            match words.as_slice() {
+                [] => return Ok(None),
+                [first, ..] if first.starts_with('#') => return Ok(None),
+                ["move", dx, dy] => Command::Move { dx: parse_number(dx)?, dy: parse_number(dy)? },
+                ["automove", dx, dy] => Command::AutoMove { dx: parse_number(dx)?, dy: parse_number(dy)? },
+                ["rest"] => Command::Rest { turns: 1 },
+                ["rest", turns] => Command::Rest { turns: parse_number(turns)? },
+                ["pickup"] => Command::PickUp,
+                ["use", rest @ ..] => {
+                    match rest {
+                        [item @ .., "at", x, y] if !item.is_empty() => Command::Use {
+                            item: item.join(" "),
+                            target: Some(Point::new(parse_number::<i32>(x)?, parse_number::<i32>(y)?))
+                        },
+                        [] => return Err("use needs an item".to_string()),
+                        item => Command::Use { item: item.join(" "), target: None }
+                    }
+                }
+                ["drop", item @ ..] if !item.is_empty() => Command::Drop { item: item.join(" ") },
+                ["dance", dance] => Command::Dance { dance: dance.to_string() },
+                ["descend"] => Command::Descend,
+                ["report"] => Command::Report,
+                _ => return Err(format!("unknown command \"{}\"", line.trim()))
+            };
+        Ok(Some(command))
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(word: &str) -> Result<T, String> {
+    word.parse().map_err(|_| format!("bad number {}", word))
+}
+
+/// Runs a script of commands, one per line, and gives the final state of the world. Any reports
+/// the script asks for along the way are handed to `reported`.
+pub fn run_script<F: FnMut(&WorldReport)>(path: &str, seed: Seed, profile: bool, reported: F) -> Result<WorldReport, String> {
+    let script = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+    let mut sim = Simulation::new(seed)?;
+    if profile {
+        sim.state.enable_profiling();
+    }
+    run_commands(&mut sim, &script, path, reported)?;
+    if let Some(report) = sim.state.profile_report() {
+        eprintln!("{}", report);
+    }
+    Ok(sim.report())
+}
+
+/// Runs each line of a script in turn until the game is over. Errors say which line of `name`
+/// they came from.
+fn run_commands<F: FnMut(&WorldReport)>(sim: &mut Simulation, script: &str, name: &str, mut reported: F) -> Result<(), String> {
+    for (i, line) in script.lines().enumerate() {
+        if sim.is_over() {
+            break;
+        }
+        let command = Command::parse(line).map_err(|e| format!("{}:{}: {}", name, i + 1, e))?;
+        if let Some(command) = command {
+            let report = sim.command(&command).map_err(|e| format!("{}:{}: {}", name, i + 1, e))?;
+            if let Some(report) = report {
+                reported(&report);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn fmt_stat(stat: Option<(i32, i32)>) -> String {
+    match stat {
+        Some((value, max_value)) => format!("{} / {}", value, max_value),
+        None => "-".to_string()
+    }
+}
+
+impl fmt::Display for ActorReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at ({}, {}): {} {}, {} {}, {} {}",
+            self.name, self.position.x, self.position.y,
+            Health::NAME, fmt_stat(self.health),
+            Stamina::NAME, fmt_stat(self.stamina),
+            Poise::NAME, fmt_stat(self.poise))?;
+        if let Some(dance) = &self.dancing {
+            write!(f, " (doing the {} dance)", dance)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for WorldReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "seed {}, turn {}, floor {}", self.seed, self.turn, self.depth)?;
+        if let Some((won, reason)) = &self.outcome {
+            writeln!(f, "game over ({}): {}", if *won { "won" } else { "lost" }, reason)?;
+        }
+        if let Some(player) = &self.player {
+            writeln!(f, "{}", player)?;
+        }
+        writeln!(f, "inventory: {}", self.inventory.join(", "))?;
+        writeln!(f, "monsters:")?;
+        for monster in self.monsters.iter() {
+            writeln!(f, "  {}", monster)?;
+        }
+        writeln!(f, "log:")?;
+        for entry in self.log.iter() {
+            writeln!(f, "  {}", entry)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rltk::Point;
+    use super::{Command, Simulation, WorldReport, run_commands};
+
+    #[test]
+    fn parses_commands() {
+        assert!(matches!(Command::parse("move -1 0"), Ok(Some(Command::Move { dx: -1, dy: 0 }))));
+        assert!(matches!(Command::parse("  rest  "), Ok(Some(Command::Rest { turns: 1 }))));
+        assert!(matches!(Command::parse("rest 20"), Ok(Some(Command::Rest { turns: 20 }))));
+        assert!(matches!(Command::parse("use water dart at 3 4"),
+            Ok(Some(Command::Use { ref item, target: Some(Point { x: 3, y: 4 }) })) if item == "water dart"));
+        assert!(matches!(Command::parse("use health kit"), Ok(Some(Command::Use { ref item, target: None })) if item == "health kit"));
+        assert!(matches!(Command::parse("# rest 20"), Ok(None)));
+        assert!(matches!(Command::parse(""), Ok(None)));
+        assert!(Command::parse("use").is_err());
+        assert!(Command::parse("rest lots").is_err());
+        assert!(Command::parse("fly 0 1").is_err());
+    }
+
+    #[test]
+    fn runs_a_script() {
+        let mut sim = Simulation::new(42).unwrap();
+        let mut reports: Vec<WorldReport> = Vec::new();
+        run_commands(&mut sim, "# wait a bit\nrest 4\nreport\nrest 4\nreport\n", "test", |report| reports.push(report.clone())).unwrap();
+
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].seed, 42);
+        assert_eq!(reports[0].depth, 1);
+        assert_eq!(reports[0].outcome, None);
+        assert!(reports[0].turn > 1 && reports[1].turn > reports[0].turn);
+        let player = reports[1].player.as_ref().unwrap();
+        assert_eq!(player.name, "player");
+        assert_eq!(player.health, Some((30, 30)));
+        assert_eq!(reports[1].turn, sim.report().turn);
+    }
+
+    #[test]
+    fn says_which_line_went_wrong() {
+        let mut sim = Simulation::new(42).unwrap();
+        let error = run_commands(&mut sim, "rest\nuse nothing much\n", "test", |_| {}).err().unwrap();
+        assert_eq!(error, "test:2: player isn't carrying nothing much");
+    }
+}
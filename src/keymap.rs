@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use rltk::{VirtualKeyCode};
+use serde::{Deserialize};
+use super::player::PlayerCommand;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const KEYS_FILE_PATH: &str = "data/keys.json";
+
+// What keys are called in the keys file and on the help screen.
+const KEY_NAMES: &[(&str, VirtualKeyCode)] = &[
+    ("a", VirtualKeyCode::A), ("b", VirtualKeyCode::B), ("c", VirtualKeyCode::C), ("d", VirtualKeyCode::D),
+    ("e", VirtualKeyCode::E), ("f", VirtualKeyCode::F), ("g", VirtualKeyCode::G), ("h", VirtualKeyCode::H),
+    ("i", VirtualKeyCode::I), ("j", VirtualKeyCode::J), ("k", VirtualKeyCode::K), ("l", VirtualKeyCode::L),
+    ("m", VirtualKeyCode::M), ("n", VirtualKeyCode::N), ("o", VirtualKeyCode::O), ("p", VirtualKeyCode::P),
+    ("q", VirtualKeyCode::Q), ("r", VirtualKeyCode::R), ("s", VirtualKeyCode::S), ("t", VirtualKeyCode::T),
+    ("u", VirtualKeyCode::U), ("v", VirtualKeyCode::V), ("w", VirtualKeyCode::W), ("x", VirtualKeyCode::X),
+    ("y", VirtualKeyCode::Y), ("z", VirtualKeyCode::Z),
+    ("0", VirtualKeyCode::Key0), ("1", VirtualKeyCode::Key1), ("2", VirtualKeyCode::Key2), ("3", VirtualKeyCode::Key3),
+    ("4", VirtualKeyCode::Key4), ("5", VirtualKeyCode::Key5), ("6", VirtualKeyCode::Key6), ("7", VirtualKeyCode::Key7),
+    ("8", VirtualKeyCode::Key8), ("9", VirtualKeyCode::Key9),
+    ("numpad0", VirtualKeyCode::Numpad0), ("numpad1", VirtualKeyCode::Numpad1), ("numpad2", VirtualKeyCode::Numpad2),
+    ("numpad3", VirtualKeyCode::Numpad3), ("numpad4", VirtualKeyCode::Numpad4), ("numpad5", VirtualKeyCode::Numpad5),
+    ("numpad6", VirtualKeyCode::Numpad6), ("numpad7", VirtualKeyCode::Numpad7), ("numpad8", VirtualKeyCode::Numpad8),
+    ("numpad9", VirtualKeyCode::Numpad9),
+    ("left", VirtualKeyCode::Left), ("right", VirtualKeyCode::Right), ("up", VirtualKeyCode::Up), ("down", VirtualKeyCode::Down),
+    ("home", VirtualKeyCode::Home), ("end", VirtualKeyCode::End), ("pageup", VirtualKeyCode::PageUp), ("pagedown", VirtualKeyCode::PageDown),
+    ("insert", VirtualKeyCode::Insert), ("delete", VirtualKeyCode::Delete),
+    ("space", VirtualKeyCode::Space), ("return", VirtualKeyCode::Return), ("tab", VirtualKeyCode::Tab),
+    ("backspace", VirtualKeyCode::Back), ("escape", VirtualKeyCode::Escape),
+    (".", VirtualKeyCode::Period), (",", VirtualKeyCode::Comma), ("/", VirtualKeyCode::Slash), ("\\", VirtualKeyCode::Backslash),
+    (";", VirtualKeyCode::Semicolon), ("'", VirtualKeyCode::Apostrophe), ("[", VirtualKeyCode::LBracket),
+    ("]", VirtualKeyCode::RBracket), ("-", VirtualKeyCode::Minus), ("=", VirtualKeyCode::Equals), ("`", VirtualKeyCode::Grave),
+    ("f1", VirtualKeyCode::F1), ("f2", VirtualKeyCode::F2), ("f3", VirtualKeyCode::F3), ("f4", VirtualKeyCode::F4),
+    ("f5", VirtualKeyCode::F5), ("f6", VirtualKeyCode::F6), ("f7", VirtualKeyCode::F7), ("f8", VirtualKeyCode::F8),
+    ("f9", VirtualKeyCode::F9), ("f10", VirtualKeyCode::F10), ("f11", VirtualKeyCode::F11), ("f12", VirtualKeyCode::F12)
+];
+
+/// Some keys to do a command, as written in the keys file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BindingDef {
+    command: PlayerCommand,
+    keys: Vec<String>,
+    help: String
+}
+
+/// Keys to do a command, with what to say about it on the help screen.
+pub struct Binding {
+    pub command: PlayerCommand,
+    pub keys: Vec<VirtualKeyCode>,
+    pub help: String
+}
+
+/// Which keys do which commands in a game. Menus keep their own keys.
+pub struct Keymap {
+    // In the order they're in the file, which is the order they're shown in.
+    pub bindings: Vec<Binding>,
+    commands: HashMap<VirtualKeyCode, PlayerCommand>,
+    // Keys bound to more than one command, which do the first.
+    pub conflicts: Vec<String>
+}
+
+impl Keymap {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> Result<Self, String> {
+        let data = std::fs::read_to_string(KEYS_FILE_PATH).map_err(|e| format!("can't read {}: {}", KEYS_FILE_PATH, e))?;
+        Keymap::parse(&data).map_err(|e| format!("{}: {}", KEYS_FILE_PATH, e))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Result<Self, String> {
+        Keymap::parse(include_str!("../data/keys.json"))
+    }
+
+    pub fn parse(data: &str) -> Result<Self, String> {
+        let defs: Vec<BindingDef> = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        let mut bindings = Vec::new();
+        let mut commands: HashMap<VirtualKeyCode, PlayerCommand> = HashMap::new();
+        let mut conflicts = Vec::new();
+        for def in defs {
+            if def.command == PlayerCommand::ShowConsole && !cfg!(debug_assertions) {
+                continue;
+            }
+            if let PlayerCommand::Move { dx, dy } = def.command {
+                if dx.abs() > 1 || dy.abs() > 1 || (dx == 0 && dy == 0) {
+                    return Err(format!("{} isn't a move of one step", def.help));
+                }
+            }
+            let mut keys = Vec::new();
+            for name in def.keys.iter() {
+                let key = KEY_NAMES.iter().find(|(n, _)| n == name).map(|(_, key)| *key).ok_or_else(|| format!("there's no key called {}", name))?;
+                match commands.get(&key) {
+                    None => { commands.insert(key, def.command.clone()); }
+                    Some(command) if *command == def.command => {}
+                    Some(command) => {
+                        let first = bindings.iter().find(|b: &&Binding| b.command == *command).map_or("", |b| b.help.as_str());
+                        conflicts.push(format!("{} is bound to both {} and {}; only the first works", name, first, def.help));
+                    }
+                }
+                keys.push(key);
+            }
+            bindings.push(Binding { command: def.command, keys, help: def.help });
+        }
+        Ok(Keymap { bindings, commands, conflicts })
+    }
+
+    pub fn command(&self, key: VirtualKeyCode) -> Option<PlayerCommand> {
+        self.commands.get(&key).cloned()
+    }
+}
+
+pub fn key_name(key: VirtualKeyCode) -> String {
+    KEY_NAMES.iter().find(|(_, k)| *k == key).map_or_else(|| format!("{:?}", key), |(name, _)| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use rltk::{VirtualKeyCode};
+    use crate::player::PlayerCommand;
+    use super::Keymap;
+
+    #[test]
+    fn keys_file_has_no_conflicts() {
+        let keymap = Keymap::load().unwrap();
+        assert_eq!(keymap.conflicts, Vec::<String>::new());
+        assert!(keymap.command(VirtualKeyCode::H) == Some(PlayerCommand::Move { dx: -1, dy: 0 }));
+    }
+
+    #[test]
+    fn first_binding_wins_a_conflict() {
+        let keymap = Keymap::parse(r#"[
+            { "command": "PickUp", "keys": ["g"], "help": "get item" },
+            { "command": "Descend", "keys": ["space", "g"], "help": "go down stairs" }
+        ]"#).unwrap();
+        assert!(keymap.command(VirtualKeyCode::G) == Some(PlayerCommand::PickUp));
+        assert!(keymap.command(VirtualKeyCode::Space) == Some(PlayerCommand::Descend));
+        assert_eq!(keymap.conflicts, vec!["g is bound to both get item and go down stairs; only the first works"]);
+    }
+
+    #[test]
+    fn console_is_only_bound_in_debug_builds() {
+        let keymap = Keymap::load().unwrap();
+        let bound = keymap.command(VirtualKeyCode::Grave) == Some(PlayerCommand::ShowConsole);
+        assert_eq!(bound, cfg!(debug_assertions));
+    }
+}
@@ -2,6 +2,7 @@ extern crate serde;
 use rltk::{Rltk, GameState};
 use specs::prelude::*;
 
+#[macro_use]
 mod components;
 pub use components::*;
 mod dancing;
@@ -15,24 +16,38 @@ pub use rect::*;
 mod drawing;
 mod gui;
 mod gamelog;
+mod events;
 mod stuff;
 mod spawner;
 mod saveload_system;
 mod systems;
-use systems::damage_system::{delete_the_dead};
 mod state;
-use state::{Turn};
+use state::{Turn, Seed};
 mod text;
 mod factions;
 mod cellinfo;
 mod win_lose_conditions;
+mod stats;
+mod scores;
+mod keymap;
+mod settings;
+mod headless;
+mod bot;
+mod cli;
+mod replay;
+mod random;
+#[cfg(test)]
+mod testing;
+#[cfg(debug_assertions)]
+mod wizard;
+use replay::InputEvent;
 
 #[derive(PartialEq, Clone)]
 pub enum RunState {
     AwaitingInput,
     PreRun,
-    PlayerTurn,
-    MonsterTurn,
+    // Things with a turn act, then turns are given out again.
+    Ticking,
     ShowInventory,
     ShowDropItem,
     ShowTargeting { range: i32, item: Entity },
@@ -40,8 +55,14 @@ pub enum RunState {
     ShowDanceMenu,
     ShowKeys,
     ShowLog,
+    #[cfg(debug_assertions)]
+    ShowConsole,
     MainMenu { menu_selection: gui::MainMenuSelection },
+    LoadGame,
     SaveGame,
+    ShowHighScores,
+    ShowRunHistory,
+    ShowOptions { selection: usize },
     NextLevel,
     GameOver { won: bool, reason: String }
 }
@@ -56,7 +77,7 @@ impl state::State {
 impl GameState for state::State {
     fn tick(&mut self, ctx : &mut Rltk) {
         ctx.cls();
-        systems::particle_system::cull_dead_particles(&mut self.ecs, ctx);
+        systems::particle_system::cull_dead_particles(&mut self.ecs, ctx.frame_time_ms);
         
         let mut newrunstate;
         {
@@ -65,41 +86,43 @@ impl GameState for state::State {
         }
 
         match newrunstate {
-            RunState::MainMenu {..} => {},
+            RunState::MainMenu {..} | RunState::LoadGame | RunState::ShowHighScores | RunState::ShowRunHistory | RunState::ShowOptions {..} => {},
             _ => { self.draw_world(ctx); }
         }
 
+        if self.replay_waiting(ctx) {
+            return;
+        }
+
         match &newrunstate {
-            RunState::PreRun => {
-                self.run_systems();
-                newrunstate = RunState::AwaitingInput;
+            RunState::PreRun | RunState::Ticking | RunState::NextLevel => {
+                newrunstate = self.advance(&newrunstate);
             }
             RunState::AwaitingInput => {
-                let mut busy = false;
-                {
-                    let player = *self.ecs.fetch::<Entity>();
-                    let dancers = self.ecs.write_storage::<Dancing>();
-                    if let Some(_) = dancers.get(player) {
-                        busy = true;
-                    }
-                }
-                if busy {
-                    newrunstate = RunState::PlayerTurn;
+                if self.player_is_busy() {
+                    newrunstate = RunState::Ticking;
                 } else {
-                    newrunstate = player_input(self, ctx);
+                    let command = match self.command_input(ctx) {
+                        Some(InputEvent::Command(command)) => Some(command),
+                        _ => None
+                    };
+                    newrunstate = player_input(self, command.clone());
+                    match command {
+                        // Looking at the log or help doesn't touch the game, so it's left out of replays,
+                        // and what the console does can't be played back anyway.
+                        Some(PlayerCommand::ShowLog) | Some(PlayerCommand::ShowKeys) | Some(PlayerCommand::ShowConsole) => {}
+                        Some(command) => self.record_input(InputEvent::Command(command)),
+                        None => {
+                            if newrunstate != RunState::AwaitingInput {
+                                self.record_input(InputEvent::Idle);
+                            }
+                        }
+                    }
                 }
             }
-            RunState::PlayerTurn => {
-                self.next_turn();
-                self.run_systems();
-                newrunstate = RunState::MonsterTurn;
-            }
-            RunState::MonsterTurn => {
-                self.run_systems();
-                newrunstate = RunState::AwaitingInput;
-            }
             RunState::ShowInventory => {
-                let result = gui::show_inventory(self, ctx);
+                let input = self.menu_input(ctx);
+                let result = gui::show_inventory(self, ctx, input);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {},
@@ -113,16 +136,17 @@ impl GameState for state::State {
                             } else {
                                 let mut intent = self.ecs.write_storage::<WantsToUseItem>();
                                 intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem{ item: item_entity, target: None }).expect("Unable to insert intent");
-                                newrunstate = RunState::PlayerTurn;
+                                newrunstate = RunState::Ticking;
                             }
                         } else {
-                            newrunstate = RunState::PlayerTurn;
+                            newrunstate = RunState::Ticking;
                         }
                     }
                 }
             }
             RunState::ShowDropItem => {
-                let result = gui::drop_item_menu(self, ctx);
+                let input = self.menu_input(ctx);
+                let result = gui::drop_item_menu(self, ctx, input);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {},
@@ -132,24 +156,26 @@ impl GameState for state::State {
                             let mut intent = self.ecs.write_storage::<WantsToDropItem>();
                             intent.insert(*self.ecs.fetch::<Entity>(), WantsToDropItem{ item: item_entity }).expect("Unable to insert intent");
                         }
-                        newrunstate = RunState::PlayerTurn;
+                        newrunstate = RunState::Ticking;
                     }
                 }
             }
             RunState::ShowTargeting{range, item} => {
-                let result = gui::ranged_target(self, ctx, *range);
+                let click = self.target_input(ctx);
+                let result = gui::ranged_target(self, ctx, *range, click);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
                     gui::ItemMenuResult::Selected => {
                         let mut intent = self.ecs.write_storage::<WantsToUseItem>();
                         intent.insert(*self.ecs.fetch::<Entity>(), WantsToUseItem{ item: *item, target: result.1 }).expect("Unable to insert intent");
-                        newrunstate = RunState::PlayerTurn;
+                        newrunstate = RunState::Ticking;
                     }
                 }
             }
             RunState::ShowRemoveItem => {
-                let result = gui::remove_item_menu(self, ctx);
+                let input = self.menu_input(ctx);
+                let result = gui::remove_item_menu(self, ctx, input);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
@@ -159,12 +185,13 @@ impl GameState for state::State {
                             let mut intent = self.ecs.write_storage::<WantsToUnequipItem>();
                             intent.insert(*self.ecs.fetch::<Entity>(), WantsToUnequipItem{ item: item_entity }).expect("Unable to insert intent");
                         }
-                        newrunstate = RunState::PlayerTurn;
+                        newrunstate = RunState::Ticking;
                     }
                 }
             }
             RunState::ShowDanceMenu => {
-                let result = gui::dance_menu(self, ctx);
+                let input = self.menu_input(ctx);
+                let result = gui::dance_menu(self, ctx, input);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
@@ -177,7 +204,7 @@ impl GameState for state::State {
                                 repetitions: 1 as u32
                             }).expect("Unable to insert intent");
                         }
-                        newrunstate = RunState::PlayerTurn;
+                        newrunstate = RunState::Ticking;
                     }
                 }
             }
@@ -189,8 +216,14 @@ impl GameState for state::State {
                     gui::ItemMenuResult::Selected => newrunstate = RunState::AwaitingInput
                 }
             }
+            #[cfg(debug_assertions)]
+            RunState::ShowConsole => {
+                if let Some(runstate) = wizard::show_console(self, ctx) {
+                    newrunstate = runstate;
+                }
+            }
             RunState::ShowKeys => {
-                let result = gui::show_keys(ctx);
+                let result = gui::show_keys(&self.ecs.fetch::<keymap::Keymap>(), ctx);
                 match result.0 {
                     gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
                     gui::ItemMenuResult::NoResponse => {}
@@ -204,36 +237,126 @@ impl GameState for state::State {
                     gui::MainMenuResult::Selected{ selected } => {
                         match selected {
                             gui::MainMenuSelection::NewGame => {
+                                self.menu_message = None;
                                 self.reset_world();
-                                self.setup_world();
+                                self.setup_world(self.new_game_seed, self.settings.auto_move);
+                                self.new_game_seed = state::random_seed();
+                                self.start_recording();
                                 newrunstate = RunState::PreRun;
                             }
                             gui::MainMenuSelection::LoadGame => {
-                                self.reset_world();
-                                saveload_system::load_game(&mut self.ecs);
-                                newrunstate = RunState::AwaitingInput;
-                                saveload_system::delete_save();
+                                newrunstate = RunState::LoadGame;
+                            }
+                            gui::MainMenuSelection::HighScores => {
+                                newrunstate = RunState::ShowHighScores;
+                            }
+                            gui::MainMenuSelection::RunHistory => {
+                                newrunstate = RunState::ShowRunHistory;
+                            }
+                            gui::MainMenuSelection::Options => {
+                                newrunstate = RunState::ShowOptions{ selection: 0 };
                             }
                             gui::MainMenuSelection::Quit => { ::std::process::exit(0); }
                         }
                     }
                 }
             }
+            RunState::ShowHighScores => {
+                if gui::high_scores(self, ctx) == gui::ItemMenuResult::Cancel {
+                    newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::HighScores };
+                }
+            }
+            RunState::ShowRunHistory => {
+                if gui::run_history(self, ctx) == gui::ItemMenuResult::Cancel {
+                    newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::RunHistory };
+                }
+            }
+            RunState::ShowOptions{ selection } => {
+                match gui::options_menu(self, ctx, *selection) {
+                    gui::OptionsMenuResult::NoSelection{ selected } => newrunstate = RunState::ShowOptions{ selection: selected },
+                    gui::OptionsMenuResult::Close => newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::Options }
+                }
+            }
+            RunState::LoadGame => {
+                let result = gui::load_game_menu(self, ctx, gui::menu_input(ctx));
+                match result {
+                    (gui::ItemMenuResult::Cancel, _) => newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::LoadGame },
+                    (gui::ItemMenuResult::Selected, Some(slot)) => {
+                        self.reset_world();
+                        match saveload_system::load_game(&mut self.ecs, slot) {
+                            Ok(()) => {
+                                self.menu_message = None;
+                                self.start_recording();
+                                newrunstate = RunState::AwaitingInput;
+                                if self.settings.permadeath {
+                                    if let Err(e) = saveload_system::delete_save(slot) {
+                                        let turn = *self.ecs.fetch::<Turn>();
+                                        let message = format!("Couldn't delete the save in slot {}: {}.", slot + 1, e);
+                                        self.ecs.write_resource::<gamelog::PlayerLog>().insert(turn, gamelog::Category::System, &message);
+                                    }
+                                    self.refresh_save_slots();
+                                }
+                            }
+                            Err(e) => {
+                                // Loading may have got partway before finding what was wrong.
+                                self.reset_world();
+                                let mut message = format!("Couldn't load slot {}: {}.", slot + 1, e);
+                                if !matches!(e, saveload_system::LoadError::Missing) {
+                                    match saveload_system::move_aside(slot) {
+                                        Ok(path) => message.push_str(&format!(" It's been moved to {}.", path)),
+                                        Err(e) => message.push_str(&format!(" It couldn't be moved aside either ({}).", e))
+                                    }
+                                }
+                                self.menu_message = Some(message);
+                                self.refresh_save_slots();
+                                newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::NewGame };
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
             RunState::SaveGame => {
-                saveload_system::save_game(&mut self.ecs);
-                newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::LoadGame };
-            },
-            RunState::NextLevel => {
-                self.goto_next_level();
-                {
-                    let map = self.ecs.read_resource::<Map>();
-                    let mut player_log = self.ecs.write_resource::<gamelog::PlayerLog>();
-                    player_log.insert(&format!("You go down stairs to floor {}.", map.depth));
+                let input = self.menu_input(ctx);
+                let result = gui::save_game_menu(self, ctx, input);
+                match result {
+                    (gui::ItemMenuResult::Cancel, _) => {
+                        self.menu_message = None;
+                        newrunstate = RunState::AwaitingInput;
+                    }
+                    (gui::ItemMenuResult::Selected, Some(slot)) => {
+                        if self.replay.is_some() {
+                            // The recorded game carried on from this save, so go through the same save and load.
+                            let data = saveload_system::save_to_string(&mut self.ecs);
+                            self.reset_world();
+                            match saveload_system::load_from_string(&mut self.ecs, &data) {
+                                Ok(()) => newrunstate = RunState::AwaitingInput,
+                                Err(e) => {
+                                    // There's no game left to carry on with, so go back to the menu.
+                                    self.reset_world();
+                                    let message = format!("The replay can't go on, as its save wouldn't load: {}.", e);
+                                    self.end_replay(&message);
+                                    self.menu_message = Some(message);
+                                    newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::NewGame };
+                                }
+                            }
+                        } else {
+                            match saveload_system::save_game(&mut self.ecs, slot) {
+                                Ok(()) => {
+                                    self.menu_message = None;
+                                    self.refresh_save_slots();
+                                    newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::LoadGame };
+                                }
+                                // Stay in the game, so the player can try another slot or carry on.
+                                Err(e) => self.menu_message = Some(format!("Couldn't save to slot {}: {}.", slot + 1, e))
+                            }
+                        }
+                    }
+                    _ => {}
                 }
-                newrunstate = RunState::PreRun;
             },
             RunState::GameOver { won, reason } => {
-                let result = gui::game_over(*won, &reason.to_string(), ctx);
+                let result = gui::game_over(*won, &reason.to_string(), self, ctx);
                 match result {
                     gui::GameOverResult::NoSelection => {}
                     gui::GameOverResult::QuitToMenu => {
@@ -244,29 +367,67 @@ impl GameState for state::State {
             }
         }
 
-        {
-            let mut runwriter = self.ecs.write_resource::<RunState>();
-            *runwriter = newrunstate.clone();
-        }
-
-        win_lose_conditions::check_lose(&mut self.ecs);
-        delete_the_dead(&mut self.ecs);
+        self.finish_tick(newrunstate);
     }
 }
 
+rltk::embedded_resource!(TERMINAL8X8, "../resources/terminal8x8.jpg");
+rltk::embedded_resource!(VGA8X16, "../resources/vga8x16.jpg");
+
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
-    let context = RltkBuilder::simple80x50()
+    let args = cli::parse_args()?;
+
+    if let Some(runs) = args.bot_runs {
+        let report = bot::play_batch(args.seed.unwrap_or(1), runs, |result| println!("{}", result))?;
+        println!("{}", report);
+        return Ok(());
+    }
+
+    if let Some(script) = args.headless_script {
+        let report = headless::run_script(&script, args.seed.unwrap_or_else(state::random_seed), args.profile, |report| println!("{}", report))?;
+        println!("{}", report);
+        return Ok(());
+    }
+
+    let replay = match args.replay {
+        Some(path) => Some(replay::load_replay(&path)?),
+        None => None
+    };
+
+    let mut gs = state::State::new();
+    if let Some(seed) = args.seed {
+        gs.new_game_seed = seed;
+    }
+    if args.profile {
+        gs.enable_profiling();
+    }
+    gs.record_runs = true;
+    gs.setup_resources();
+    gs.load_data()?;
+    gs.load_settings();
+    gs.report_key_conflicts();
+    gs.refresh_save_slots();
+    gs.load_scores();
+
+    rltk::link_resource!(TERMINAL8X8, "resources/terminal8x8.jpg");
+    rltk::link_resource!(VGA8X16, "resources/vga8x16.jpg");
+    let (width, height) = gs.settings.window_size;
+    let font = gs.settings.font;
+    let (tile_width, tile_height) = font.tile_size();
+    let context = RltkBuilder::new()
+        .with_dimensions(width, height)
+        .with_tile_dimensions(tile_width, tile_height)
         .with_title("Roguelike Tutorial")
+        .with_font(font.file(), tile_width, tile_height)
+        .with_simple_console(width, height, font.file())
         .build()?;
-    let mut gs = state::State::new();
-    setup_ecs(&mut gs.ecs);
-    gs.ecs.insert(KeyState{ requested_auto_move: false });
-    gs.ecs.insert(RunState::MainMenu { menu_selection: gui::MainMenuSelection::LoadGame });
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
-    gs.ecs.insert(systems::particle_system::ParticleBuilder::new());
-    gs.ecs.insert(gamelog::PlayerLog::new());
-    gs.ecs.insert(gamelog::GameLog::new());
-    gs.setup_world();
+    if let Some(replay) = replay {
+        gs.ecs.insert(RunState::PreRun);
+        gs.start_replay(replay);
+    } else {
+        gs.ecs.insert(RunState::MainMenu { menu_selection: gui::MainMenuSelection::LoadGame });
+        gs.setup_world(gs.new_game_seed, gs.settings.auto_move);
+    }
     rltk::main_loop(context, gs)
 }
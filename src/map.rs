@@ -4,7 +4,7 @@ use rltk::{Point, BaseMap, Algorithm2D};
 use specs::prelude::*;
 use super::{Rect}; 
 use serde::{Serialize, Deserialize};
-use crate::{liquids::Liquid};
+use crate::{liquids::Liquid, random::RandomNumberGenerator};
 
 pub const MAPWIDTH: usize = 80;
 pub const MAPHEIGHT: usize = 50;
@@ -65,7 +65,22 @@ impl Map {
         point.x >= 0 && point.x < self.width && point.y >= 0 && point.y < self.height
     }
 
-    pub fn new(new_depth: i32, rng: &mut rltk::RandomNumberGenerator) -> Map {
+    /// Generates a map for a level, trying again until one passes `validate`. A map that fails
+    /// is a bug in the generator, so this gives up rather than going on forever.
+    pub fn new(new_depth: i32, rng: &mut RandomNumberGenerator) -> Map {
+        const MAX_ATTEMPTS: i32 = 100;
+        let mut problem = String::new();
+        for _ in 0..MAX_ATTEMPTS {
+            let map = Map::generate(new_depth, rng);
+            match map.validate() {
+                Ok(()) => return map,
+                Err(e) => problem = e
+            }
+        }
+        panic!("No valid map after {} attempts; the last failed because {}", MAX_ATTEMPTS, problem);
+    }
+
+    fn generate(new_depth: i32, rng: &mut RandomNumberGenerator) -> Map {
         let mut map = Map {
             tiles: vec![TileType::Wall; MAPCOUNT],
             rooms: Vec::new(),
@@ -119,6 +134,54 @@ impl Map {
         map
     }
 
+    /// Checks the map is fit to play: the border is solid wall, and the stairs and every other
+    /// floor tile can be reached from the middle of the first room, where the player starts.
+    pub fn validate(&self) -> Result<(), String> {
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let on_border = x == 0 || y == 0 || x == self.width - 1 || y == self.height - 1;
+                if on_border && self.tiles[self.xy_idx(x, y)] != TileType::Wall {
+                    return Err(format!("the border is open at ({}, {})", x, y));
+                }
+            }
+        }
+        let stairs = self.tiles.iter().position(|tile| *tile == TileType::DownStairs)
+            .ok_or_else(|| "there are no stairs down".to_string())?;
+        let (start_x, start_y) = self.rooms.first().ok_or_else(|| "there are no rooms".to_string())?.centre();
+        let start = self.xy_idx(start_x, start_y);
+        if self.tiles[start] == TileType::Wall {
+            return Err(format!("the first room starts in a wall at ({}, {})", start_x, start_y));
+        }
+
+        // Anything that can move can move diagonally, so that counts as connected.
+        let mut reached = vec![false; self.tiles.len()];
+        reached[start] = true;
+        let mut open = vec![start];
+        while let Some(idx) = open.pop() {
+            let x = idx as i32 % self.width;
+            let y = idx as i32 / self.width;
+            for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)].iter() {
+                let next = Point::new(x + dx, y + dy);
+                if !self.point_valid(&next) {
+                    continue;
+                }
+                let next_idx = self.point_idx(&next);
+                if !reached[next_idx] && self.tiles[next_idx] != TileType::Wall {
+                    reached[next_idx] = true;
+                    open.push(next_idx);
+                }
+            }
+        }
+
+        if !reached[stairs] {
+            return Err(format!("the stairs at ({}, {}) can't be reached", stairs as i32 % self.width, stairs as i32 / self.width));
+        }
+        if let Some(idx) = (0..self.tiles.len()).find(|&idx| self.tiles[idx] != TileType::Wall && !reached[idx]) {
+            return Err(format!("the floor at ({}, {}) can't be reached", idx as i32 % self.width, idx as i32 / self.width));
+        }
+        Ok(())
+    }
+
     pub fn populate_blocked(&mut self) {
         for (i, tile) in self.tiles.iter_mut().enumerate() {
             self.blocked[i] = *tile == TileType::Wall;
@@ -142,18 +205,18 @@ impl Map {
 
     fn apply_horizontal_tunnel(&mut self, x1: i32, x2: i32, y: i32) {
         for x in min(x1, x2) ..= max(x1, x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
+            if self.point_valid(&Point::new(x, y)) {
+                let idx = self.xy_idx(x, y);
+                self.tiles[idx] = TileType::Floor;
             }
         }
     }
 
     fn apply_vertical_tunnel(&mut self, y1: i32, y2: i32, x: i32) {
         for y in min(y1, y2) ..= max(y1, y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
+            if self.point_valid(&Point::new(x, y)) {
+                let idx = self.xy_idx(x, y);
+                self.tiles[idx] = TileType::Floor;
             }
         }
     }
@@ -275,3 +338,70 @@ impl<'a> Algorithm2D for WallOnlyMapPather<'a> {
         Point::new(self.map.width, self.map.height)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::draw_map;
+    use crate::random::RandomNumberGenerator;
+    use super::Map;
+
+    // Straight from the generator, so a problem isn't hidden by `Map::new` trying again.
+    #[test]
+    fn generated_maps_are_valid() {
+        for seed in 0..2000 {
+            let mut rng = RandomNumberGenerator::seeded(seed);
+            for depth in 1..=3 {
+                if let Err(e) = Map::generate(depth, &mut rng).validate() {
+                    panic!("seed {} depth {}: {}", seed, depth, e);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn connected_map_is_valid() {
+        let map = draw_map("
+            #######
+            #@.#..#
+            #..#.>#
+            ###.###
+            #######");
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn open_border_is_invalid() {
+        let map = draw_map("
+            ###.###
+            #@...>#
+            #######");
+        assert_eq!(map.validate(), Err("the border is open at (3, 0)".to_string()));
+    }
+
+    #[test]
+    fn missing_stairs_are_invalid() {
+        let map = draw_map("
+            #####
+            #@..#
+            #####");
+        assert_eq!(map.validate(), Err("there are no stairs down".to_string()));
+    }
+
+    #[test]
+    fn unreachable_stairs_are_invalid() {
+        let map = draw_map("
+            #######
+            #@.#.>#
+            #######");
+        assert_eq!(map.validate(), Err("the stairs at (5, 1) can't be reached".to_string()));
+    }
+
+    #[test]
+    fn unreachable_floor_is_invalid() {
+        let map = draw_map("
+            ########
+            #@.>##.#
+            ########");
+        assert_eq!(map.validate(), Err("the floor at (6, 1) can't be reached".to_string()));
+    }
+}
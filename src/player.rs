@@ -1,20 +1,22 @@
-use rltk::{Rltk, VirtualKeyCode, Point};
+use rltk::{Point};
+use serde::{Serialize, Deserialize};
 use specs::prelude::*;
 use std::cmp::{max, min};
-use super::{state::State, Position, Player, Map, RunState, Health, WantsToMelee, WantsToPickupItem, Item, gamelog::PlayerLog, TileType, systems::auto_movement_system, WantsToMove, Resting, Confusion, Equipped, EquipmentSlot}; 
+use super::{state::{State, Turn}, Position, Player, Map, RunState, Health, WantsToMelee, WantsToPickupItem, Item, gamelog::{PlayerLog, Category}, TileType, systems::auto_movement_system, WantsToMove, Resting, Confusion, Equipped, EquipmentSlot}; 
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct KeyState {
     pub requested_auto_move: bool
 }
 
-fn skip_turn(ecs: &mut World) -> RunState {
+pub fn skip_turn(ecs: &mut World) -> RunState {
     let player_entity = ecs.fetch::<Entity>();
     let confusion = ecs.read_storage::<Confusion>();
     if let Some(_) = confusion.get(*player_entity) {
         let mut resting = ecs.write_storage::<Resting>();
         resting.insert(*player_entity, Resting {}).expect("Failed to insert resting.");
     }
-    RunState::PlayerTurn
+    RunState::Ticking
 }
 
 pub fn player_can_act(ecs: &mut World) -> bool {
@@ -23,7 +25,7 @@ pub fn player_can_act(ecs: &mut World) -> bool {
 
     if let Some(_) = confusion.get(player_entity) {
         let mut player_log = ecs.fetch_mut::<PlayerLog>();
-        player_log.insert(&"You are confused and cannot act.".to_string());
+        player_log.insert(*ecs.fetch::<Turn>(), Category::System, &"You are confused and cannot act.");
         false
     } else {
         true
@@ -89,13 +91,14 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
     }
 }
 
-fn get_item(ecs: &mut World) {
+pub fn get_item(ecs: &mut World) {
     let player_pos = ecs.fetch::<Point>();
     let player_entity = ecs.fetch::<Entity>();
     let entities = ecs.entities();
     let items = ecs.read_storage::<Item>();
     let positions = ecs.read_storage::<Position>();
     let mut player_log = ecs.fetch_mut::<PlayerLog>();
+    let turn = *ecs.fetch::<Turn>();
 
     let mut target_item: Option<Entity> = None;
     for (item_entity, _item, position) in (&entities, &items, &positions).join() {
@@ -105,7 +108,7 @@ fn get_item(ecs: &mut World) {
     }
 
     match target_item {
-        None => player_log.entries.push("There is nothing here to pick up.".to_string()),
+        None => player_log.insert(turn, Category::Item, &"There is nothing here to pick up."),
         Some(item) => {
             let mut pickup = ecs.write_storage::<WantsToPickupItem>();
             pickup.insert(*player_entity, WantsToPickupItem{ collected_by: *player_entity, item }).expect("Unable to insert want to pickup");
@@ -113,7 +116,7 @@ fn get_item(ecs: &mut World) {
     }
 }
 
-fn try_next_level(ecs: &mut World) -> bool {
+pub fn try_next_level(ecs: &mut World) -> bool {
     let player_pos = ecs.fetch::<Point>();
     let map = ecs.fetch::<Map>();
     let player_idx = map.xy_idx(player_pos.x, player_pos.y);
@@ -121,92 +124,84 @@ fn try_next_level(ecs: &mut World) -> bool {
         true
     } else {
         let mut player_log = ecs.fetch_mut::<PlayerLog>();
-        player_log.insert(&"There is no way down from here.");
+        player_log.insert(*ecs.fetch::<Turn>(), Category::System, &"There is no way down from here.");
         false
     }
 }
 
-fn handle_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    match ctx.key {
-        None => { return RunState::AwaitingInput },
-        Some(key) => match key {
-            VirtualKeyCode::Left |
-            VirtualKeyCode::Numpad4 |
-            VirtualKeyCode::H => try_move_player(-1, 0, &mut gs.ecs),
-
-            VirtualKeyCode::Right |
-            VirtualKeyCode::Numpad6 |
-            VirtualKeyCode::L => try_move_player(1, 0, &mut gs.ecs),
-
-            VirtualKeyCode::Up |
-            VirtualKeyCode::Numpad8 |
-            VirtualKeyCode::K => try_move_player(0, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Down |
-            VirtualKeyCode::Numpad2 |
-            VirtualKeyCode::J => try_move_player(0, 1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad9 |
-            VirtualKeyCode::Y => try_move_player(-1, -1, &mut gs.ecs),
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
+pub enum PlayerCommand {
+    Move { dx: i32, dy: i32 },
+    AutoMove,
+    SkipTurn,
+    Descend,
+    PickUp,
+    ShowInventory,
+    ShowDropItem,
+    ShowRemoveItem,
+    ShowDanceMenu,
+    ShowLog,
+    ShowKeys,
+    SaveGame,
+    // Only debug builds have the console; release builds leave its keys out of the keymap.
+    ShowConsole
+}
 
-            VirtualKeyCode::Numpad7 |
-            VirtualKeyCode::U => try_move_player(1, -1, &mut gs.ecs),
+fn do_command(gs: &mut State, command: &PlayerCommand) -> RunState {
+    match command {
+        PlayerCommand::Move { dx, dy } => try_move_player(*dx, *dy, &mut gs.ecs),
 
-            VirtualKeyCode::Numpad3 |
-            VirtualKeyCode::N => try_move_player(1, 1, &mut gs.ecs),
+        PlayerCommand::AutoMove => {
+            let mut state = gs.ecs.fetch_mut::<KeyState>();
+            state.requested_auto_move = true;
+        }
 
-            VirtualKeyCode::Numpad1 |
-            VirtualKeyCode::B => try_move_player(-1, 1, &mut gs.ecs),
+        PlayerCommand::SkipTurn => return skip_turn(&mut gs.ecs),
 
-            VirtualKeyCode::A => {
-                let mut state = gs.ecs.fetch_mut::<KeyState>();
-                state.requested_auto_move = true;
+        PlayerCommand::Descend => {
+            if try_next_level(&mut gs.ecs) {
+                return RunState::NextLevel;
             }
+        }
 
-            VirtualKeyCode::Numpad5 |
-            VirtualKeyCode::Period => {
-                return skip_turn(&mut gs.ecs)
-            },
-
-            VirtualKeyCode::Space => {
-                if try_next_level(&mut gs.ecs) {
-                    return RunState::NextLevel;
-                }
-            },
-
-            VirtualKeyCode::G => get_item(&mut gs.ecs),
+        PlayerCommand::PickUp => get_item(&mut gs.ecs),
 
-            VirtualKeyCode::I => return RunState::ShowInventory,
+        PlayerCommand::ShowInventory => return RunState::ShowInventory,
 
-            VirtualKeyCode::D => return RunState::ShowDropItem,
+        PlayerCommand::ShowDropItem => return RunState::ShowDropItem,
 
-            VirtualKeyCode::R => return RunState::ShowRemoveItem,
+        PlayerCommand::ShowRemoveItem => return RunState::ShowRemoveItem,
 
-            VirtualKeyCode::Z => return RunState::ShowDanceMenu,
+        PlayerCommand::ShowDanceMenu => return RunState::ShowDanceMenu,
 
-            VirtualKeyCode::M => return RunState::ShowLog,
+        PlayerCommand::ShowLog => return RunState::ShowLog,
 
-            VirtualKeyCode::Slash => return RunState::ShowKeys,
+        PlayerCommand::ShowKeys => return RunState::ShowKeys,
 
-            VirtualKeyCode::Escape => return RunState::SaveGame,
+        PlayerCommand::SaveGame => return RunState::SaveGame,
 
-            _ => { return RunState::AwaitingInput }
-        },
+        #[cfg(debug_assertions)]
+        PlayerCommand::ShowConsole => return RunState::ShowConsole,
+        #[cfg(not(debug_assertions))]
+        PlayerCommand::ShowConsole => return RunState::AwaitingInput
     }
 
-    RunState::PlayerTurn
+    RunState::Ticking
 }
 
-pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    let newrunstate = handle_input(gs, ctx);
+pub fn player_input(gs: &mut State, command: Option<PlayerCommand>) -> RunState {
+    let newrunstate = match command {
+        None => RunState::AwaitingInput,
+        Some(command) => do_command(gs, &command)
+    };
     let player_entity = *gs.ecs.fetch::<Entity>();
     match newrunstate {
         RunState::AwaitingInput => {
             if auto_movement_system::is_auto_moving(&gs.ecs, player_entity) {
-                return RunState::PlayerTurn
+                return RunState::Ticking
             }
         }
-        RunState::PlayerTurn => {}
+        RunState::Ticking => {}
         _ => {
             auto_movement_system::stop(&mut gs.ecs, player_entity);
         }
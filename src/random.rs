@@ -0,0 +1,29 @@
+use rand::{Rng, SeedableRng};
+use rand_xorshift::XorShiftRng;
+use serde::{Serialize, Deserialize};
+
+/// The same generator as `rltk::RandomNumberGenerator`, giving the same numbers from the same seed,
+/// but with state that can be saved.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RandomNumberGenerator {
+    rng: XorShiftRng
+}
+
+impl RandomNumberGenerator {
+    pub fn seeded(seed: u64) -> RandomNumberGenerator {
+        RandomNumberGenerator { rng: XorShiftRng::seed_from_u64(seed) }
+    }
+
+    /// Gives a number from `min` up to but not including `max`.
+    pub fn range<T>(&mut self, min: T, max: T) -> T
+    where
+        T: rand::distributions::uniform::SampleUniform
+    {
+        self.rng.gen_range(min, max)
+    }
+
+    /// Rolls `n` dice with `die_type` sides each, giving the total.
+    pub fn roll_dice(&mut self, n: i32, die_type: i32) -> i32 {
+        (0..n).map(|_| self.range(1, die_type + 1)).sum()
+    }
+}
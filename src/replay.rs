@@ -0,0 +1,241 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use serde::{Serialize, Deserialize};
+use rltk::{Rltk, Point};
+use crate::{state::{State, Seed, Turn}, systems::auto_movement_system::AutoMoveRules, player::PlayerCommand, keymap::Keymap, gui::{self, MenuInput}, gamelog::{PlayerLog, Category}};
+
+pub const REPLAY_FILE_PATH: &str = "./replay.jsonl";
+
+// Replays run one tick per step, slow enough to follow.
+const REPLAY_STEP_MS: f32 = 40.0;
+
+/// Input that the game acted on, at the point where it read it.
+#[derive(PartialEq, Clone, Serialize, Deserialize)]
+pub enum InputEvent {
+    Command(PlayerCommand),
+    // A tick with no input that still moved the game on, like a step of auto-movement.
+    Idle,
+    Menu(MenuInput),
+    Target(Point)
+}
+
+// Replays and saves from before there were auto-move rules went by the defaults.
+#[derive(Serialize, Deserialize)]
+struct ReplayHeader {
+    seed: Seed,
+    #[serde(default)]
+    auto_move: AutoMoveRules,
+    #[serde(default)]
+    tainted: bool
+}
+
+/// The seed, rules and input of the current game so far, enough to play it again.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InputRecord {
+    pub seed: Seed,
+    #[serde(default)]
+    pub auto_move: AutoMoveRules,
+    pub events: Vec<InputEvent>,
+    // Set once the debug console has changed the game, which the input alone can't play back.
+    #[serde(default)]
+    pub tainted: bool
+}
+
+pub struct Replay {
+    events: VecDeque<InputEvent>,
+    wait_ms: f32
+}
+
+impl InputRecord {
+    pub fn new(seed: Seed, auto_move: AutoMoveRules) -> Self {
+        InputRecord { seed, auto_move, events: Vec::new(), tainted: false }
+    }
+}
+
+impl Replay {
+    pub fn new(events: Vec<InputEvent>) -> Self {
+        Replay { events: events.into(), wait_ms: 0.0 }
+    }
+
+    /// Counts down the frame time, saying whether it's time for the next step.
+    pub fn ready(&mut self, frame_time_ms: f32) -> bool {
+        self.wait_ms -= frame_time_ms;
+        if self.wait_ms > 0.0 {
+            return false;
+        }
+        self.wait_ms = REPLAY_STEP_MS;
+        true
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// Reads a replay file: a header line with the seed, then one input event per line.
+pub fn load_replay(path: &str) -> Result<InputRecord, String> {
+    let data = fs::read_to_string(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+    let mut lines = data.lines().enumerate();
+    let header: ReplayHeader = match lines.next() {
+        Some((_, line)) => serde_json::from_str(line).map_err(|e| format!("{}:1: {}", path, e))?,
+        None => return Err(format!("{} is empty", path))
+    };
+    if header.tainted {
+        return Err(format!("{} is from a game the debug console changed, so it can't be played back", path));
+    }
+    let mut record = InputRecord::new(header.seed, header.auto_move);
+    for (i, line) in lines {
+        let event = serde_json::from_str(line).map_err(|e| format!("{}:{}: {}", path, i + 1, e))?;
+        record.events.push(event);
+    }
+    Ok(record)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_record(record: &InputRecord) -> io::Result<()> {
+    let mut data = serde_json::to_string(&ReplayHeader { seed: record.seed, auto_move: record.auto_move, tainted: record.tainted })?;
+    data.push('\n');
+    for event in record.events.iter() {
+        data.push_str(&serde_json::to_string(event)?);
+        data.push('\n');
+    }
+    fs::write(REPLAY_FILE_PATH, data)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_record(_record: &InputRecord) -> io::Result<()> {
+    Ok(())
+}
+
+// Events are appended as they happen, so the replay survives a crash.
+#[cfg(not(target_arch = "wasm32"))]
+fn append_event(event: &InputEvent) -> io::Result<()> {
+    use std::io::Write;
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(REPLAY_FILE_PATH)?;
+    writeln!(file, "{}", serde_json::to_string(event)?)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn append_event(_event: &InputEvent) -> io::Result<()> {
+    Ok(())
+}
+
+impl State {
+    pub fn start_replay(&mut self, record: InputRecord) {
+        self.setup_world(record.seed, record.auto_move);
+        self.replay = Some(Replay::new(record.events));
+    }
+
+    /// Goes back to live input, recording from where the replay stopped.
+    pub fn end_replay(&mut self, message: &str) {
+        self.replay = None;
+        let turn = *self.ecs.fetch::<Turn>();
+        self.ecs.fetch_mut::<PlayerLog>().insert(turn, Category::System, &message);
+        self.start_recording();
+    }
+
+    /// Writes the game so far to the replay file and carries on adding to it, unless a replay is
+    /// running.
+    pub fn start_recording(&mut self) {
+        if self.replay.is_none() {
+            self.recording = true;
+            let written = write_record(&self.ecs.fetch::<InputRecord>());
+            if let Err(e) = written {
+                self.stop_recording(e);
+            }
+        }
+    }
+
+    /// Marks the game as no longer replayable, in the replay file straight away and in any saves
+    /// from now on.
+    pub fn taint_recording(&mut self) {
+        if self.ecs.fetch::<InputRecord>().tainted {
+            return;
+        }
+        self.ecs.fetch_mut::<InputRecord>().tainted = true;
+        if self.replay.is_none() && self.recording {
+            let written = write_record(&self.ecs.fetch::<InputRecord>());
+            if let Err(e) = written {
+                self.stop_recording(e);
+            }
+        }
+    }
+
+    // A game that can't be recorded is still worth playing, so this just says why it stopped.
+    fn stop_recording(&mut self, e: io::Error) {
+        self.recording = false;
+        let turn = *self.ecs.fetch::<Turn>();
+        let message = format!("Couldn't write {}, so the rest of this game won't be in the replay: {}.", REPLAY_FILE_PATH, e);
+        self.ecs.fetch_mut::<PlayerLog>().insert(turn, Category::System, &message);
+    }
+
+    /// Says whether a running replay is waiting out its step, or ends it if it has run out.
+    pub fn replay_waiting(&mut self, ctx: &Rltk) -> bool {
+        match &mut self.replay {
+            None => false,
+            Some(replay) if replay.is_finished() => {
+                self.end_replay("The replay is over.");
+                false
+            }
+            Some(replay) => !replay.ready(ctx.frame_time_ms)
+        }
+    }
+
+    pub fn command_input(&mut self, ctx: &Rltk) -> Option<InputEvent> {
+        let live = ctx.key.and_then(|key| self.ecs.fetch::<Keymap>().command(key)).map(InputEvent::Command);
+        match self.next_input(live) {
+            Some(InputEvent::Menu(_)) | Some(InputEvent::Target(_)) => {
+                self.end_replay("The replay is out of step with the game.");
+                None
+            }
+            event => event
+        }
+    }
+
+    pub fn menu_input(&mut self, ctx: &Rltk) -> Option<MenuInput> {
+        let live = gui::menu_input(ctx).map(InputEvent::Menu);
+        match self.next_input(live) {
+            None => None,
+            Some(InputEvent::Menu(input)) => {
+                self.record_input(InputEvent::Menu(input));
+                Some(input)
+            }
+            Some(_) => {
+                self.end_replay("The replay is out of step with the game.");
+                None
+            }
+        }
+    }
+
+    pub fn target_input(&mut self, ctx: &mut Rltk) -> Option<Point> {
+        let live = gui::target_click(self, ctx).map(InputEvent::Target);
+        match self.next_input(live) {
+            None => None,
+            Some(InputEvent::Target(target)) => {
+                self.record_input(InputEvent::Target(target));
+                Some(target)
+            }
+            Some(_) => {
+                self.end_replay("The replay is out of step with the game.");
+                None
+            }
+        }
+    }
+
+    pub fn record_input(&mut self, event: InputEvent) {
+        if self.replay.is_none() && self.recording {
+            if let Err(e) = append_event(&event) {
+                self.stop_recording(e);
+            }
+        }
+        self.ecs.fetch_mut::<InputRecord>().events.push(event);
+    }
+
+    fn next_input(&mut self, live: Option<InputEvent>) -> Option<InputEvent> {
+        match &mut self.replay {
+            None => live,
+            Some(replay) => replay.events.pop_front()
+        }
+    }
+}
@@ -1,75 +1,499 @@
 use specs::prelude::*;
-use specs::saveload::{SimpleMarker, SimpleMarkerAllocator, SerializeComponents, DeserializeComponents, MarkedBuilder};
-use specs::error::NoError;
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator, MarkerAllocator, SerializeComponents, DeserializeComponents, MarkedBuilder, ConvertSaveload};
 use super::components::*;
+use serde_json::{Value, Map as JsonMap, json};
+use super::dancing::DanceDefs;
+use super::stuff::StuffDefs;
+use super::systems::{initiative, auto_movement_system::AutoMoveRules};
+use super::random::RandomNumberGenerator;
+use super::player::KeyState;
+use super::stats::{Stats, RunStats};
+use super::text::time_text;
+use super::state::{Turn, Seed};
+use serde::{Serialize, Deserialize};
 use std::fs::File;
 use std::path::Path;
 use std::fs;
+use std::fmt;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-const SAVE_FILE_PATH: &str = "./savegame.json";
+const SAVE_DIR: &str = "./saves";
 
+// Where the only save went before there were slots.
+const OLD_SAVE_FILE_PATH: &str = "./savegame.json";
+
+pub const SAVE_SLOTS: usize = 5;
+
+/// Bump this whenever a change to the saved components would stop older saves loading,
+/// and add a migration from the old version to `migrate`.
+const SAVE_VERSION: u64 = 8;
+
+// Version 1 saves had no header, just each of these components' storage, one after another.
+const V1_COMPONENTS: &[&str] = &["Position", "Renderable", "Player", "Viewshed", "Monster", "MonsterAI", "Name", "BlocksTile", "Health", "CombatStats", "SufferDamage", "WantsToMelee", "Item", "Consumable", "Ranged", "InflictsDamage", "AreaOfEffect", "CausesConfusion", "Confusion", "ProvidesHealing", "InBackpack", "WantsToPickupItem", "WantsToUseItem", "WantsToDropItem", "WantsToUnequipItem", "Equippable", "Equipped", "MeleePowerBonus", "DefenceBonus", "SerializationHelper", "ParticleLifetime", "Dancing", "Poise", "EffectRequest", "Awestruck", "HasAggroedMosters", "WantsToMove", "SpreadsLiquid", "InFaction", "Stamina", "MakeNoise", "Noise", "ProvidesStamina", "ProvidesPoise"];
+
+/// What the slot pickers show about a save, kept in its header so it can be read without loading.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaveSummary {
+    pub depth: i32,
+    pub turn: Turn,
+    pub health: (i32, i32),
+    pub poise: (i32, i32),
+    pub seed: Seed,
+    // Seconds since the Unix epoch.
+    pub saved_at: u64
+}
+
+#[derive(Clone)]
+pub enum SaveSlot {
+    Empty,
+    // Saves from before summaries were added have none.
+    Saved(Option<SaveSummary>)
+}
+
+/// Why a save couldn't be loaded.
+pub enum LoadError {
+    Missing,
+    Unreadable(io::Error),
+    Damaged(String),
+    TooNew(u64),
+    CantMigrate { version: u64, reason: String }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Missing => write!(f, "the save has gone missing"),
+            LoadError::Unreadable(e) => write!(f, "the save can't be read ({})", e),
+            LoadError::Damaged(reason) => write!(f, "the save is damaged ({})", reason),
+            LoadError::TooNew(version) => write!(f, "the save is from a newer version of the game (save version {}, this game reads up to {})", version, SAVE_VERSION),
+            LoadError::CantMigrate { version, reason } => write!(f, "the save is from an older version of the game that can't be brought up to date (save version {}: {})", version, reason)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct SaveHeader {
+    summary: Option<SaveSummary>
+}
+
+impl SaveSummary {
+    pub fn saved_at_text(&self) -> String {
+        time_text(self.saved_at)
+    }
+}
+
+fn slot_path(slot: usize) -> String {
+    format!("{}/slot{}.json", SAVE_DIR, slot + 1)
+}
+
+fn summarise(ecs: &World) -> SaveSummary {
+    let players = ecs.read_storage::<Player>();
+    let healths = ecs.read_storage::<Health>();
+    let poises = ecs.read_storage::<Poise>();
+    let health = (&players, &healths).join().next().map_or((0, 0), |(_, h)| (h.health, h.max_health));
+    let poise = (&players, &poises).join().next().map_or((0, 0), |(_, p)| (p.poise, p.max_poise));
+    SaveSummary {
+        depth: ecs.fetch::<super::map::Map>().depth,
+        turn: *ecs.fetch::<Turn>(),
+        health,
+        poise,
+        seed: *ecs.fetch::<Seed>(),
+        saved_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+    }
+}
+
+// Both of these go through the components in `with_components!` one at a time.
 macro_rules! serialize_individually {
-    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty ), *) => {
+    ($ecs:expr, $components:expr, $data:expr, [ $( $type:ident )* ]) => {
         $(
-        SerializeComponents::<NoError, SimpleMarker<SerializeMe>>::serialize(
-            &( $ecs.read_storage::<$type>(), ),
-            &$data.0,
-            &$data.1,
-            &mut $ser,
-        )
-        .unwrap();
+            $components.insert(
+                stringify!($type).to_string(),
+                SerializeComponents::<Unsaved, SimpleMarker<SerializeMe>>::serialize(
+                    &( $ecs.read_storage::<$type>(), ),
+                    &$data.0,
+                    &$data.1,
+                    serde_json::value::Serializer,
+                )?
+            );
         )*
     };
 }
 
 macro_rules! deserialize_individually {
-    ($ecs:expr, $de:expr, $data:expr, $( $type:ty ), *) => {
+    ($ecs:expr, $components:expr, $data:expr, [ $( $type:ident )* ]) => {
         $(
-        DeserializeComponents::<NoError, _>::deserialize(
-            &mut ( &mut $ecs.write_storage::<$type>(), ),
-            &mut $data.0,
-            &mut $data.1,
-            &mut $data.2,
-            &mut $de,
-        )
-        .unwrap();
+            if let Some(storage) = $components.remove(stringify!($type)) {
+                DeserializeComponents::<Unsaved, _>::deserialize(
+                    &mut ( &mut $ecs.write_storage::<$type>(), ),
+                    &mut $data.0,
+                    &mut $data.1,
+                    &mut $data.2,
+                    storage,
+                )
+                .map_err(|e| LoadError::Damaged(format!("bad {} data: {}", stringify!($type), e)))?;
+            }
         )*
     };
 }
 
-#[cfg(not(target_arch = "wasm32"))]
-pub fn save_game(ecs: &mut World) {
+fn serialize_world<W: Write>(ecs: &mut World, writer: W) -> io::Result<()> {
+    forget_the_departed(ecs);
     let map_copy = ecs.get_mut::<super::map::Map>().unwrap().clone();
     let player_log_copy = ecs.get_mut::<super::gamelog::PlayerLog>().unwrap().clone();
+    let seed = *ecs.fetch::<super::Seed>();
+    let input_record_copy = ecs.get_mut::<super::replay::InputRecord>().unwrap().clone();
+    let turn = *ecs.fetch::<Turn>();
+    let rng_copy = ecs.get_mut::<RandomNumberGenerator>().unwrap().clone();
+    let key_state_copy = ecs.get_mut::<KeyState>().unwrap().clone();
+    let stats_copy = ecs.fetch::<Stats>().run.clone();
+    let summary = summarise(ecs);
     let savehelper = ecs
         .create_entity()
         .with(SerializationHelper{
             map: map_copy,
             player_log: player_log_copy,
+            seed,
+            input_record: input_record_copy,
+            turn,
+            rng: rng_copy,
+            key_state: key_state_copy,
+            stats: stats_copy
         })
         .marked::<SimpleMarker<SerializeMe>>()
         .build();
 
-    {
-        let data = (ecs.entities(), ecs.read_storage::<SimpleMarker<SerializeMe>>());
-        let writer = File::create(SAVE_FILE_PATH).unwrap();
-        let mut serializer = serde_json::Serializer::new(writer);
+    let written = serialize_components(ecs).and_then(|components| {
+        let save = json!({ "version": SAVE_VERSION, "summary": summary, "components": components });
+        serde_json::to_writer(writer, &save)
+    });
+
+    ecs.delete_entity(savehelper).expect("Crash on cleanup");
+    written.map_err(io::Error::from)
+}
+
+fn serialize_components(ecs: &World) -> serde_json::Result<JsonMap<String, Value>> {
+    let data = (ecs.entities(), ecs.read_storage::<SimpleMarker<SerializeMe>>());
+    let mut components = JsonMap::new();
+    with_components!(serialize_individually!(ecs, components, data,));
+    Ok(components)
+}
+
+// Who hurt or awed something is remembered after they're gone, but a save can only refer to
+// things that are still there.
+fn forget_the_departed(ecs: &mut World) {
+    let entities = ecs.entities();
+    let mut hurt_by = ecs.write_storage::<HurtBy>();
+    let mut awed_by = ecs.write_storage::<AwedBy>();
+    let gone: Vec<Entity> = (&entities, &hurt_by).join().filter(|(_, h)| !entities.is_alive(h.by)).map(|(e, _)| e).collect();
+    for entity in gone {
+        hurt_by.remove(entity);
+    }
+    let gone: Vec<Entity> = (&entities, &awed_by).join().filter(|(_, a)| !entities.is_alive(a.by)).map(|(e, _)| e).collect();
+    for entity in gone {
+        awed_by.remove(entity);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_game(ecs: &mut World, slot: usize) -> io::Result<()> {
+    fs::create_dir_all(SAVE_DIR)?;
+    let writer = File::create(slot_path(slot))?;
+    serialize_world(ecs, writer)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_game(_ecs: &mut World, _slot: usize) -> io::Result<()> {
+    Ok(())
+}
+
+/// Saves the game the same way as `save_game`, but into a string instead of the save file.
+pub fn save_to_string(ecs: &mut World) -> String {
+    let mut data = Vec::new();
+    serialize_world(ecs, &mut data).expect("Unable to write save");
+    String::from_utf8(data).expect("Save isn't valid UTF-8")
+}
 
-        serialize_individually!(ecs, serializer, data, Position, Renderable, Player, Viewshed, Monster, MonsterAI, Name, BlocksTile, Health, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage, AreaOfEffect, CausesConfusion, Confusion, ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem, WantsToDropItem, WantsToUnequipItem, Equippable, Equipped, MeleePowerBonus, DefenceBonus, SerializationHelper, ParticleLifetime, Dancing, Poise, EffectRequest, Awestruck, HasAggroedMosters, WantsToMove, SpreadsLiquid, InFaction, Stamina, MakeNoise, Noise, ProvidesStamina, ProvidesPoise);
+/// Moves a save from before slots into the first slot, if there is one and the slot is free.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn move_old_save() -> io::Result<()> {
+    if Path::new(OLD_SAVE_FILE_PATH).exists() && !Path::new(&slot_path(0)).exists() {
+        fs::create_dir_all(SAVE_DIR)?;
+        fs::rename(OLD_SAVE_FILE_PATH, slot_path(0))?;
     }
+    Ok(())
+}
 
-    ecs.delete_entity(savehelper).expect("Crash on cleanup")
+#[cfg(target_arch = "wasm32")]
+pub fn move_old_save() -> io::Result<()> {
+    Ok(())
+}
+
+/// Says what's in each slot.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_slots() -> Vec<SaveSlot> {
+    (0..SAVE_SLOTS).map(|slot| {
+        match fs::read_to_string(slot_path(slot)) {
+            Err(_) => SaveSlot::Empty,
+            Ok(data) => SaveSlot::Saved(serde_json::from_str::<SaveHeader>(&data).ok().and_then(|h| h.summary))
+        }
+    }).collect()
 }
 
 #[cfg(target_arch = "wasm32")]
-pub fn save_game(_ecs: &mut World) {
+pub fn save_slots() -> Vec<SaveSlot> {
+    vec![SaveSlot::Empty; SAVE_SLOTS]
 }
 
-pub fn does_save_exist() -> bool {
-    Path::new(SAVE_FILE_PATH).exists()
+fn save_version(save: &Value) -> Result<u64, LoadError> {
+    match save {
+        Value::Array(_) => Ok(1),
+        Value::Object(fields) => fields.get("version").and_then(Value::as_u64).ok_or_else(|| LoadError::Damaged("no version".to_string())),
+        _ => Err(LoadError::Damaged("not in any format this game knows".to_string()))
+    }
+}
+
+/// Brings a save from whatever version wrote it up to the current version.
+fn migrate(mut save: Value, ecs: &World) -> Result<Value, LoadError> {
+    let mut version = save_version(&save)?;
+    if version > SAVE_VERSION {
+        return Err(LoadError::TooNew(version));
+    }
+    while version < SAVE_VERSION {
+        save = match version {
+            1 => migrate_v1(save, ecs),
+            2 => migrate_v2(save, ecs),
+            3 => migrate_v3(save, ecs),
+            4 => migrate_v4(save),
+            5 => migrate_v5(save),
+            6 => migrate_v6(save),
+            7 => migrate_v7(save),
+            _ => Err("no migration from this version".to_string())
+        }.map_err(|reason| LoadError::CantMigrate { version, reason })?;
+        version += 1;
+    }
+    Ok(save)
+}
+
+// Version 2 keys each storage by its component's name, and changed these since version 1:
+// particles stopped being saved, the helper gained the seed and input record, and dances
+// went from built-in names to data.
+fn migrate_v1(save: Value, ecs: &World) -> Result<Value, String> {
+    let storages = match save {
+        Value::Array(storages) => storages,
+        _ => return Err("expected a list of storages".to_string())
+    };
+    if storages.len() != V1_COMPONENTS.len() {
+        return Err(format!("expected {} storages, found {}", V1_COMPONENTS.len(), storages.len()));
+    }
+    let mut components: JsonMap<String, Value> = V1_COMPONENTS.iter().map(|name| name.to_string()).zip(storages).collect();
+    components.remove("ParticleLifetime");
+
+    // The seed wasn't saved, so the rest of the game can't be replayed; start the record afresh.
+    if let Some(Value::Array(helpers)) = components.get_mut("SerializationHelper") {
+        for helper in helpers.iter_mut() {
+            if let Some(data) = helper.pointer_mut("/components/0").and_then(Value::as_object_mut) {
+                data.insert("seed".to_string(), json!(0));
+                data.insert("input_record".to_string(), json!({ "seed": 0, "events": [] }));
+            }
+        }
+    }
+
+    let dances = ecs.fetch::<DanceDefs>();
+    let mut components = Value::Object(components);
+    replace_v1_dances(&mut components, &dances)?;
+
+    Ok(json!({ "version": 2, "components": components }))
+}
+
+// Version 3 saves the turn, the state of the random number generator, the key state, and
+// the components that were left out before. Those that only last partway through an action
+// start empty. The dances known and the noise items make are part of what the stuff is, so
+// they're worked out again from its name.
+fn migrate_v2(mut save: Value, ecs: &World) -> Result<Value, String> {
+    let helpers = save.pointer_mut("/components/SerializationHelper").and_then(Value::as_array_mut).ok_or("no helper")?;
+    for helper in helpers.iter_mut() {
+        if let Some(data) = helper.pointer_mut("/components/0").and_then(Value::as_object_mut) {
+            // Loading used to reseed the generator and start counting turns again, so carry on doing that.
+            let seed = data.get("seed").and_then(Value::as_u64).ok_or("helper has no seed")?;
+            let rng = serde_json::to_value(RandomNumberGenerator::seeded(seed)).expect("Unable to convert generator");
+            data.insert("turn".to_string(), json!(0));
+            data.insert("rng".to_string(), rng);
+            data.insert("key_state".to_string(), json!({ "requested_auto_move": false }));
+        }
+    }
+
+    let stuff = ecs.fetch::<StuffDefs>();
+    let dances = ecs.fetch::<DanceDefs>();
+    save["components"]["CanDoDances"] = storage_from_names(&save, |name| {
+        let known = dances.known_by(name);
+        let def = stuff.get(name)?;
+        if known.is_empty() {
+            return None;
+        }
+        Some(CanDoDances{ dances: known, descriptors: def.dance_descriptors.clone() })
+    })?;
+    save["components"]["MakesNoise"] = storage_from_names(&save, |name| {
+        let noise = stuff.get(name)?.makes_noise.clone()?;
+        Some(MakesNoise{ volume: noise.volume, surprising: noise.surprising, description: noise.description })
+    })?;
+
+    save["version"] = json!(3);
+    Ok(save)
+}
+
+// Version 4 gives things turns by how much energy they have instead of taking turns in
+// lockstep. Saves were made on the player's turn, so the player has the turn and enough
+// energy for it, and everything else starts from nothing.
+fn migrate_v3(mut save: Value, ecs: &World) -> Result<Value, String> {
+    let stuff = ecs.fetch::<StuffDefs>();
+    save["components"]["Energy"] = storage_from_names(&save, |name| {
+        let def = stuff.get(name).filter(|def| def.player || def.monster)?;
+        let energy = if def.player { initiative::TURN_ENERGY } else { 0 };
+        Some(Energy{ energy, speed: def.speed.unwrap_or(initiative::NORMAL_SPEED) })
+    })?;
+    save["components"]["MyTurn"] = storage_from_names(&save, |name| {
+        stuff.get(name).filter(|def| def.player).map(|_| MyTurn{ cost: initiative::ACTION_COST })
+    })?;
+    save["components"]["SpeedPenalty"] = storage_from_names(&save, |name| {
+        stuff.get(name).and_then(|def| def.speed_penalty).map(|penalty| SpeedPenalty{ penalty })
+    })?;
+
+    save["version"] = json!(4);
+    Ok(save)
+}
+
+// Version 5 gives each message in the player's log a category and a count of repeats. Old
+// messages didn't say what they were about, so they all go under system.
+fn migrate_v4(mut save: Value) -> Result<Value, String> {
+    let helpers = save.pointer_mut("/components/SerializationHelper").and_then(Value::as_array_mut).ok_or("no helper")?;
+    for helper in helpers.iter_mut() {
+        if let Some(entries) = helper.pointer_mut("/components/0/player_log/entries").and_then(Value::as_array_mut) {
+            for entry in entries.iter_mut() {
+                let message = entry.as_str().ok_or("log message isn't text")?.to_string();
+                *entry = json!({ "category": "System", "message": message, "repeats": 1 });
+            }
+        }
+    }
+
+    save["version"] = json!(5);
+    Ok(save)
 }
 
-pub fn load_game(ecs: &mut World) {
+// Version 6 notes the turn each message in the player's log came up on. Nobody knows for
+// older messages, so they're put at the start of the game.
+fn migrate_v5(mut save: Value) -> Result<Value, String> {
+    let helpers = save.pointer_mut("/components/SerializationHelper").and_then(Value::as_array_mut).ok_or("no helper")?;
+    for helper in helpers.iter_mut() {
+        if let Some(entries) = helper.pointer_mut("/components/0/player_log/entries").and_then(Value::as_array_mut) {
+            for entry in entries.iter_mut() {
+                entry.as_object_mut().ok_or("log entry isn't an object")?.insert("turn".to_string(), json!(0));
+            }
+        }
+    }
+
+    save["version"] = json!(6);
+    Ok(save)
+}
+
+// Version 7 keeps stats on the run, and awe remembers who caused it. Nothing was counted
+// before, so the stats start from nothing. Awe is only there partway through a turn, so
+// there shouldn't be any, but nobody knows who caused it if there is.
+fn migrate_v6(mut save: Value) -> Result<Value, String> {
+    let helpers = save.pointer_mut("/components/SerializationHelper").and_then(Value::as_array_mut).ok_or("no helper")?;
+    for helper in helpers.iter_mut() {
+        if let Some(data) = helper.pointer_mut("/components/0").and_then(Value::as_object_mut) {
+            data.insert("stats".to_string(), serde_json::to_value(RunStats::default()).expect("Unable to convert stats"));
+        }
+    }
+    if let Some(Value::Array(awestruck)) = save.pointer_mut("/components/Awestruck") {
+        for entry in awestruck.iter_mut() {
+            entry["components"] = json!([null]);
+        }
+    }
+
+    save["version"] = json!(7);
+    Ok(save)
+}
+
+// Version 8 remembers who last hurt and who last awed each thing, so the player only gets the
+// credit for what they did. Nobody knows for older saves, so nobody gets it.
+fn migrate_v7(mut save: Value) -> Result<Value, String> {
+    save["version"] = json!(8);
+    Ok(save)
+}
+
+// Makes a storage for a component that can be worked out from the name of each saved entity.
+fn storage_from_names<C, F>(save: &Value, component: F) -> Result<Value, String>
+where
+    C: ConvertSaveload<SimpleMarker<SerializeMe>>,
+    C::Error: fmt::Debug,
+    F: Fn(&str) -> Option<C>
+{
+    let names = save.pointer("/components/Name").and_then(Value::as_array).ok_or("no names")?;
+    Ok(names.iter().map(|entry| {
+        let data = entry.pointer("/components/0/name").and_then(Value::as_str).and_then(&component)
+            .map(|c| serde_json::to_value(c.convert_into(|_| None).expect("Unable to convert component")).expect("Unable to convert component"));
+        json!({ "marker": entry["marker"], "components": [data] })
+    }).collect())
+}
+
+// Version 1 dances were saved as their upper case names, wherever a component held one.
+fn replace_v1_dances(value: &mut Value, dances: &DanceDefs) -> Result<(), String> {
+    match value {
+        Value::Object(fields) => {
+            for (key, field) in fields.iter_mut() {
+                if let (true, Value::String(old_name)) = (key == "dance", &field) {
+                    let name = old_name.to_lowercase();
+                    let dance = dances.get(&name).ok_or_else(|| format!("save uses the {} dance, which isn't defined any more", name))?;
+                    *field = serde_json::to_value(dance).expect("Unable to convert dance");
+                } else {
+                    replace_v1_dances(field, dances)?;
+                }
+            }
+        }
+        Value::Array(values) => {
+            for v in values.iter_mut() {
+                replace_v1_dances(v, dances)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+fn parse_save(data: &str, ecs: &World) -> Result<JsonMap<String, Value>, LoadError> {
+    let save = if data.trim_start().starts_with('[') {
+        // Version 1: a stream of values rather than one.
+        let storages = serde_json::Deserializer::from_str(data).into_iter::<Value>()
+            .collect::<Result<Vec<Value>, _>>()
+            .map_err(|e| LoadError::Damaged(format!("not valid JSON: {}", e)))?;
+        Value::Array(storages)
+    } else {
+        serde_json::from_str(data).map_err(|e| LoadError::Damaged(format!("not valid JSON: {}", e)))?
+    };
+    match migrate(save, ecs)? {
+        Value::Object(mut fields) => match fields.remove("components") {
+            Some(Value::Object(components)) => Ok(components),
+            _ => Err(LoadError::Damaged("no components".to_string()))
+        },
+        _ => Err(LoadError::Damaged("not an object".to_string()))
+    }
+}
+
+// Every storage lists every saved entity, in the order they were saved.
+fn saved_markers(components: &JsonMap<String, Value>) -> Result<Vec<u64>, LoadError> {
+    let storage = components.values().next().and_then(Value::as_array).ok_or_else(|| LoadError::Damaged("no entities".to_string()))?;
+    storage.iter()
+        .map(|entry| entry.pointer("/marker/0").and_then(Value::as_u64).ok_or_else(|| LoadError::Damaged("bad marker".to_string())))
+        .collect()
+}
+
+// Only parsing and migrating the save happen before the world is cleared. Anything wrong with
+// the components, the map or the player is found after that, leaving the world half loaded.
+fn deserialize_world(ecs: &mut World, data: &str) -> Result<(), LoadError> {
+    let mut components = parse_save(data, ecs)?;
+
     {
         let mut to_delete = Vec::new();
         for e in ecs.entities().join() {
@@ -80,26 +504,55 @@ pub fn load_game(ecs: &mut World) {
         }
     }
 
-    let data = fs::read_to_string(SAVE_FILE_PATH).unwrap();
-    let mut de = serde_json::Deserializer::from_str(&data);
+    // Systems work through entities in the order of their ids, but loading would give them out in
+    // whatever order the allocator has them free. So make the entities first, in the saved order.
+    let markers = saved_markers(&components)?;
+    let mut entities: Vec<Entity> = markers.iter().map(|_| ecs.create_entity().build()).collect();
+    entities.sort();
+    {
+        let mut allocator = ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>();
+        let mut marker_storage = ecs.write_storage::<SimpleMarker<SerializeMe>>();
+        for (id, entity) in markers.into_iter().zip(entities) {
+            let marker = allocator.allocate(entity, Some(id));
+            marker_storage.insert(entity, marker).expect("Unable to mark entity");
+        }
+    }
 
     {
         let mut d = (&mut ecs.entities(), &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(), &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>());
-        deserialize_individually!(ecs, de, d, Position, Renderable, Player, Viewshed, Monster, MonsterAI, Name, BlocksTile, Health, CombatStats, SufferDamage, WantsToMelee, Item, Consumable, Ranged, InflictsDamage, AreaOfEffect, CausesConfusion, Confusion, ProvidesHealing, InBackpack, WantsToPickupItem, WantsToUseItem, WantsToDropItem, WantsToUnequipItem, Equippable, Equipped, MeleePowerBonus, DefenceBonus, SerializationHelper, ParticleLifetime, Dancing, Poise, EffectRequest, Awestruck, HasAggroedMosters, WantsToMove, SpreadsLiquid, InFaction, Stamina, MakeNoise, Noise, ProvidesStamina, ProvidesPoise);
+        with_components!(deserialize_individually!(ecs, components, d,));
+    }
+    if let Some(name) = components.keys().next() {
+        return Err(LoadError::Damaged(format!("{} data, which this version doesn't know", name)));
     }
 
     let mut deleteme: Option<Entity> = None;
+    let mut found_player = false;
     {
         let entities = ecs.entities();
         let helper = ecs.read_storage::<SerializationHelper>();
         let player = ecs.read_storage::<Player>();
         let position = ecs.read_storage::<Position>();
         for (e, h) in (&entities, &helper).join() {
+            if !map_fits(&h.map) {
+                return Err(LoadError::Damaged("map is the wrong size".to_string()));
+            }
             let mut map = ecs.write_resource::<super::map::Map>();
             *map = h.map.clone();
-            map.tile_content = vec![Vec::new(); super::map::MAPCOUNT];
             let mut player_log = ecs.write_resource::<super::gamelog::PlayerLog>();
             *player_log = h.player_log.clone();
+            let mut seed = ecs.write_resource::<super::Seed>();
+            *seed = h.seed;
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            *rng = h.rng.clone();
+            let mut turn = ecs.write_resource::<Turn>();
+            *turn = h.turn;
+            let mut key_state = ecs.write_resource::<KeyState>();
+            *key_state = h.key_state.clone();
+            ecs.write_resource::<Stats>().run = h.stats.clone();
+            let mut input_record = ecs.write_resource::<super::replay::InputRecord>();
+            *input_record = h.input_record.clone();
+            *ecs.write_resource::<AutoMoveRules>() = h.input_record.auto_move;
             deleteme = Some(e);
         }
         for (e, _p, pos) in (&entities, &player, &position).join() {
@@ -107,13 +560,191 @@ pub fn load_game(ecs: &mut World) {
             *ppos = rltk::Point::new(pos.x, pos.y);
             let mut player_resource = ecs.write_resource::<Entity>();
             *player_resource = e;
+            found_player = true;
         }
     }
-    ecs.delete_entity(deleteme.unwrap()).expect("Unable to delete helper");
+    let helper = deleteme.ok_or_else(|| LoadError::Damaged("no map".to_string()))?;
+    ecs.delete_entity(helper).expect("Unable to delete helper");
+    {
+        // The map's index of what's where isn't saved. Blocked tiles are, since they only
+        // catch up with movement and deaths when the systems next run.
+        let mut map = ecs.write_resource::<super::map::Map>();
+        map.tile_content = vec![Vec::new(); super::map::MAPCOUNT];
+        let entities = ecs.entities();
+        let positions = ecs.read_storage::<Position>();
+        for (entity, pos) in (&entities, &positions).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            map.tile_content[idx].push(entity);
+        }
+    }
+    if !found_player {
+        return Err(LoadError::Damaged("no player".to_string()));
+    }
+    Ok(())
+}
+
+// Everything else indexes the map's tiles without checking, so a short map would crash later.
+fn map_fits(map: &super::map::Map) -> bool {
+    let count = super::map::MAPCOUNT;
+    map.width as usize * map.height as usize == count
+        && map.tiles.len() == count
+        && map.revealed_tiles.len() == count
+        && map.visible_tiles.len() == count
+        && map.blocked.len() == count
+        && map.stains.len() == count
 }
 
-pub fn delete_save() {
-    if Path::new(SAVE_FILE_PATH).exists() {
-        std::fs::remove_file(SAVE_FILE_PATH).expect("Unable to delete file");
+/// Loads the save in a slot over the current game. If it fails, the world may be left half
+/// loaded, so it needs resetting before it's used again.
+pub fn load_game(ecs: &mut World, slot: usize) -> Result<(), LoadError> {
+    let data = fs::read_to_string(slot_path(slot)).map_err(|e| {
+        match e.kind() {
+            io::ErrorKind::NotFound => LoadError::Missing,
+            _ => LoadError::Unreadable(e)
+        }
+    })?;
+    deserialize_world(ecs, &data)
+}
+
+/// Loads a save made by `save_to_string` over the current game, leaving it as `load_game` does
+/// if it fails.
+pub fn load_from_string(ecs: &mut World, data: &str) -> Result<(), LoadError> {
+    deserialize_world(ecs, data)
+}
+
+/// Moves a save that wouldn't load out of its slot, giving where it went.
+pub fn move_aside(slot: usize) -> io::Result<String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let mut path = format!("{}/slot{}-unloadable-{}.json", SAVE_DIR, slot + 1, now);
+    let mut n = 1;
+    while Path::new(&path).exists() {
+        n += 1;
+        path = format!("{}/slot{}-unloadable-{}-{}.json", SAVE_DIR, slot + 1, now, n);
+    }
+    fs::rename(slot_path(slot), &path)?;
+    Ok(path)
+}
+
+pub fn delete_save(slot: usize) -> io::Result<()> {
+    let path = slot_path(slot);
+    if Path::new(&path).exists() {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+    use serde_json::{Value, json};
+    use crate::testing::Fixture;
+    use crate::replay::InputRecord;
+    use crate::systems::{initiative, auto_movement_system::AutoMoveRules};
+    use crate::state::Seed;
+    use crate::gamelog::PlayerLog;
+    use rltk::Point;
+    use crate::{Name, CanDoDances, Energy, MyTurn, MakesNoise, Dancing, ItemUseInProgress};
+    use super::{deserialize_world, serialize_world, LoadError, SAVE_VERSION};
+
+    // Written by `save_game` as it was before saves had versions: the player part way through
+    // the jitter dance, a rabbit, a firecracker and some coffee.
+    const V1_SAVE: &str = include_str!("../data/tests/v1_save.json");
+
+    // A world ready to load into. Loading replaces the map and everything on it.
+    fn world() -> Fixture {
+        let mut fixture = Fixture::new("@");
+        fixture.ecs.insert::<Seed>(0);
+        fixture.ecs.insert(InputRecord::new(0, AutoMoveRules::default()));
+        fixture
+    }
+
+    fn named(fixture: &Fixture, name: &str) -> Entity {
+        let entities = fixture.ecs.entities();
+        let names = fixture.ecs.read_storage::<Name>();
+        (&entities, &names).join().find(|(_, n)| n.name == name).map(|(e, _)| e)
+            .unwrap_or_else(|| panic!("no {} in the loaded world", name))
+    }
+
+    fn dance_names(dances: &CanDoDances) -> Vec<String> {
+        dances.dances.iter().map(|dance| dance.name.clone()).collect()
+    }
+
+    #[test]
+    fn loads_a_version_1_save() {
+        let mut fixture = world();
+        if let Err(e) = deserialize_world(&mut fixture.ecs, V1_SAVE) {
+            panic!("Unable to load: {}", e);
+        }
+
+        let player = named(&fixture, "player");
+        let rabbit = named(&fixture, "rabbit");
+        let firecracker = named(&fixture, "firecracker");
+        let coffee = named(&fixture, "coffee");
+        assert_eq!(*fixture.ecs.fetch::<Entity>(), player);
+
+        assert!(!dance_names(&fixture.get::<CanDoDances>(player).unwrap()).is_empty());
+        assert!(!dance_names(&fixture.get::<CanDoDances>(rabbit).unwrap()).is_empty());
+        assert!(fixture.get::<CanDoDances>(coffee).is_none());
+
+        let energy = fixture.get::<Energy>(player).unwrap();
+        assert_eq!((energy.energy, energy.speed), (initiative::TURN_ENERGY, initiative::NORMAL_SPEED));
+        assert_eq!(fixture.get::<MyTurn>(player).unwrap().cost, initiative::ACTION_COST);
+        let energy = fixture.get::<Energy>(rabbit).unwrap();
+        assert_eq!((energy.energy, energy.speed), (0, 150));
+        assert!(fixture.get::<MyTurn>(rabbit).is_none());
+        assert!(fixture.get::<Energy>(firecracker).is_none());
+
+        let noise = fixture.get::<MakesNoise>(firecracker).unwrap();
+        assert_eq!((noise.volume, noise.surprising, noise.description.as_str()), (20, true, "a bang"));
+        assert!(fixture.get::<MakesNoise>(coffee).is_none());
+
+        let dancing = fixture.get::<Dancing>(player).unwrap();
+        assert_eq!(dancing.dance.name, "jitter");
+        assert_eq!(dancing.steps.len(), dancing.dance.steps.len());
+        assert_eq!(dancing.repetitions, 2);
+
+        let log = fixture.ecs.fetch::<PlayerLog>();
+        let entries: Vec<(u32, String)> = log.all().map(|entry| (entry.turn, entry.text())).collect();
+        assert_eq!(entries, vec![(0, "You start the jitter dance.".to_string())]);
+    }
+
+    #[test]
+    fn refuses_a_save_from_a_newer_version() {
+        let mut fixture = world();
+        let save = json!({ "version": SAVE_VERSION + 1, "components": {} }).to_string();
+
+        match deserialize_world(&mut fixture.ecs, &save) {
+            Err(LoadError::TooNew(version)) => assert_eq!(version, SAVE_VERSION + 1),
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(()) => panic!("loaded a save from the future")
+        }
+    }
+
+    #[test]
+    fn says_when_an_old_save_cant_be_migrated() {
+        let mut fixture = world();
+        // A version 1 save with one storage too few, so the rest can't be matched up.
+        let mut storages: Vec<Value> = serde_json::Deserializer::from_str(V1_SAVE).into_iter::<Value>()
+            .collect::<Result<_, _>>().unwrap();
+        storages.pop();
+        let save: String = storages.iter().map(Value::to_string).collect();
+
+        match deserialize_world(&mut fixture.ecs, &save) {
+            Err(LoadError::CantMigrate { version, .. }) => assert_eq!(version, 1),
+            Err(e) => panic!("wrong error: {}", e),
+            Ok(()) => panic!("loaded a save with a storage missing")
+        }
+    }
+
+    #[test]
+    fn wont_save_using_an_item_that_isnt_saved() {
+        let mut fixture = world();
+        let player = fixture.player();
+        let item = fixture.place(1, 1).build();
+        fixture.insert(player, ItemUseInProgress{ item, targets_centre: Point::new(1, 1), target_tiles: Vec::new(), targets: Vec::new() });
+
+        let written = serialize_world(&mut fixture.ecs, Vec::new());
+
+        assert!(written.unwrap_err().to_string().contains("the item it refers to isn't saved"));
     }
 }
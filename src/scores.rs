@@ -0,0 +1,139 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::io;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Serialize, Deserialize};
+use super::{state::{Turn, Seed}, stats::RunStats};
+
+const SCORES_FILE_PATH: &str = "./scores.json";
+
+// Winning earns this much, less a point for every turn it took.
+const WIN_BONUS: u64 = 5000;
+
+/// How one finished game went.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunRecord {
+    pub seed: Seed,
+    pub won: bool,
+    pub reason: String,
+    pub depth: i32,
+    pub turn: Turn,
+    pub stats: RunStats,
+    // Seconds since the Unix epoch.
+    pub ended_at: u64
+}
+
+impl RunRecord {
+    pub fn new(seed: Seed, won: bool, reason: &str, depth: i32, turn: Turn, stats: &RunStats) -> Self {
+        RunRecord {
+            seed,
+            won,
+            reason: reason.to_string(),
+            depth,
+            turn,
+            stats: stats.clone(),
+            ended_at: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+        }
+    }
+
+    /// Each floor reached counts most, then monsters killed and poise taken off them by awing.
+    /// Turns only count against a win, so that dying early never scores more.
+    pub fn score(&self) -> u64 {
+        let mut score = self.depth.max(0) as u64 * 1000
+            + self.stats.monsters_killed as u64 * 100
+            + self.stats.poise_inflicted.max(0) as u64 * 10;
+        if self.won {
+            score += WIN_BONUS.saturating_sub(self.turn as u64);
+        }
+        score
+    }
+}
+
+/// Every finished game, in the order they ended. Kept in one file, which is the high score
+/// table when sorted by score and the run history as it is.
+#[derive(Default, Serialize, Deserialize)]
+pub struct Scores {
+    runs: Vec<RunRecord>
+}
+
+impl Scores {
+    /// Reads the scores file. There being no file yet is fine, but one that can't be read isn't,
+    /// so nothing gets written over it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> Result<Self, String> {
+        match fs::read_to_string(SCORES_FILE_PATH) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Scores::default()),
+            Err(e) => Err(format!("{} can't be read ({})", SCORES_FILE_PATH, e)),
+            Ok(data) => serde_json::from_str(&data).map_err(|e| format!("{} is damaged ({})", SCORES_FILE_PATH, e))
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Result<Self, String> {
+        Ok(Scores::default())
+    }
+
+    pub fn add(&mut self, run: RunRecord) -> io::Result<()> {
+        self.runs.push(run);
+        self.save()
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&self) -> io::Result<()> {
+        let data = serde_json::to_string(self).expect("Unable to convert scores");
+        fs::write(SCORES_FILE_PATH, data)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn save(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// The best runs first. Equal scores keep the earlier run above.
+    pub fn high_scores(&self) -> Vec<&RunRecord> {
+        let mut runs: Vec<&RunRecord> = self.runs.iter().collect();
+        runs.sort_by_key(|run| Reverse(run.score()));
+        runs
+    }
+
+    /// The latest runs first.
+    pub fn history(&self) -> impl Iterator<Item = &RunRecord> {
+        self.runs.iter().rev()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::RunStats;
+    use super::{RunRecord, Scores};
+
+    fn run(won: bool, depth: i32, turn: u32, monsters_killed: u32, poise_inflicted: i32) -> RunRecord {
+        let stats = RunStats { monsters_killed, poise_inflicted, ..RunStats::default() };
+        RunRecord::new(1, won, "", depth, turn, &stats)
+    }
+
+    #[test]
+    fn scores_depth_kills_and_awe() {
+        assert_eq!(run(false, 2, 300, 3, 7).score(), 2000 + 300 + 70);
+    }
+
+    #[test]
+    fn quicker_wins_score_more() {
+        assert_eq!(run(true, 4, 1200, 0, 0).score(), 4000 + 3800);
+        assert!(run(true, 4, 1000, 0, 0).score() > run(true, 4, 2000, 0, 0).score());
+        assert_eq!(run(true, 4, 9000, 0, 0).score(), 4000);
+    }
+
+    #[test]
+    fn high_scores_are_best_first_and_history_latest_first() {
+        let mut scores = Scores::default();
+        scores.runs.push(run(false, 1, 10, 0, 0));
+        scores.runs.push(run(false, 3, 10, 0, 0));
+        scores.runs.push(run(false, 2, 10, 0, 0));
+
+        let depths: Vec<i32> = scores.high_scores().iter().map(|run| run.depth).collect();
+        assert_eq!(depths, vec![3, 2, 1]);
+        let depths: Vec<i32> = scores.history().map(|run| run.depth).collect();
+        assert_eq!(depths, vec![2, 3, 1]);
+    }
+}
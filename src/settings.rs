@@ -0,0 +1,201 @@
+use std::fs;
+use std::io;
+use serde::{Serialize, Deserialize};
+use super::systems::auto_movement_system::AutoMoveRules;
+
+const SETTINGS_FILE_PATH: &str = "./settings.json";
+
+/// The window sizes to choose from, in characters. The screens are laid out for at least 80x50.
+pub const WINDOW_SIZES: &[(u32, u32)] = &[(80, 50), (100, 60), (120, 70)];
+
+/// How long particles last, as a percentage of what each one asks for. Nought turns them off.
+pub const PARTICLE_TIMES: &[u32] = &[0, 50, 100, 200];
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Font {
+    Terminal8x8,
+    Vga8x16
+}
+
+impl Font {
+    /// The font's file in the resources folder.
+    pub fn file(self) -> &'static str {
+        match self {
+            Font::Terminal8x8 => "terminal8x8.jpg",
+            Font::Vga8x16 => "vga8x16.jpg"
+        }
+    }
+
+    pub fn tile_size(self) -> (u32, u32) {
+        match self {
+            Font::Terminal8x8 => (8, 8),
+            Font::Vga8x16 => (8, 16)
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Font::Terminal8x8 => "8x8",
+            Font::Vga8x16 => "VGA 8x16"
+        }
+    }
+}
+
+/// The things on the options screen, in the order they're shown.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Setting {
+    Font,
+    WindowSize,
+    ParticleTime,
+    StopOnSighting,
+    StopAtOpenings,
+    Permadeath
+}
+
+pub const SETTINGS: &[Setting] = &[
+    Setting::Font, Setting::WindowSize, Setting::ParticleTime, Setting::StopOnSighting, Setting::StopAtOpenings, Setting::Permadeath
+];
+
+impl Setting {
+    pub fn name(self) -> &'static str {
+        match self {
+            Setting::Font => "Font",
+            Setting::WindowSize => "Window size",
+            Setting::ParticleTime => "Particle time",
+            Setting::StopOnSighting => "Auto move stops at new sightings",
+            Setting::StopAtOpenings => "Auto move stops at openings",
+            Setting::Permadeath => "Permadeath"
+        }
+    }
+
+    /// What to know about changing it, such as it not taking effect straight away.
+    pub fn note(self) -> Option<&'static str> {
+        match self {
+            Setting::Font | Setting::WindowSize => Some("on restart"),
+            Setting::StopOnSighting | Setting::StopAtOpenings => Some("in new games"),
+            Setting::ParticleTime => None,
+            Setting::Permadeath => Some("loading deletes the save")
+        }
+    }
+}
+
+/// Everything the options screen sets, kept between runs of the game. Anything missing from the
+/// file is left at its default, so new settings don't stop older files loading.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub font: Font,
+    pub window_size: (u32, u32),
+    pub particle_time: u32,
+    // For new games; each game keeps the rules it started with.
+    pub auto_move: AutoMoveRules,
+    // Whether loading a save deletes it.
+    pub permadeath: bool
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            font: Font::Terminal8x8,
+            window_size: WINDOW_SIZES[0],
+            particle_time: 100,
+            auto_move: AutoMoveRules::default(),
+            permadeath: true
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings file, going with the defaults if there isn't one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> Result<Self, String> {
+        match fs::read_to_string(SETTINGS_FILE_PATH) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Settings::default()),
+            Err(e) => Err(format!("{} can't be read ({})", SETTINGS_FILE_PATH, e)),
+            Ok(data) => {
+                let settings: Settings = serde_json::from_str(&data).map_err(|e| format!("{} is damaged ({})", SETTINGS_FILE_PATH, e))?;
+                if !WINDOW_SIZES.contains(&settings.window_size) {
+                    return Err(format!("{} has a window size of {}x{}, which isn't one of the choices", SETTINGS_FILE_PATH, settings.window_size.0, settings.window_size.1));
+                }
+                Ok(settings)
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Result<Self, String> {
+        Ok(Settings::default())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) -> io::Result<()> {
+        let data = serde_json::to_string_pretty(self).expect("Unable to convert settings");
+        fs::write(SETTINGS_FILE_PATH, data)
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn value_text(&self, setting: Setting) -> String {
+        match setting {
+            Setting::Font => self.font.name().to_string(),
+            Setting::WindowSize => format!("{}x{}", self.window_size.0, self.window_size.1),
+            Setting::ParticleTime if self.particle_time == 0 => "off".to_string(),
+            Setting::ParticleTime => format!("{}%", self.particle_time),
+            Setting::StopOnSighting => on_off(self.auto_move.stop_on_sighting),
+            Setting::StopAtOpenings => on_off(self.auto_move.stop_at_openings),
+            Setting::Permadeath => on_off(self.permadeath)
+        }
+    }
+
+    /// Moves the setting on to its next choice, or back to the one before, going round at the ends.
+    pub fn change(&mut self, setting: Setting, forward: bool) {
+        match setting {
+            Setting::Font => self.font = step(&[Font::Terminal8x8, Font::Vga8x16], self.font, forward),
+            Setting::WindowSize => self.window_size = step(WINDOW_SIZES, self.window_size, forward),
+            Setting::ParticleTime => self.particle_time = step(PARTICLE_TIMES, self.particle_time, forward),
+            Setting::StopOnSighting => self.auto_move.stop_on_sighting = !self.auto_move.stop_on_sighting,
+            Setting::StopAtOpenings => self.auto_move.stop_at_openings = !self.auto_move.stop_at_openings,
+            Setting::Permadeath => self.permadeath = !self.permadeath
+        }
+    }
+}
+
+fn on_off(on: bool) -> String {
+    if on { "on" } else { "off" }.to_string()
+}
+
+// A value not among the choices steps to the first one.
+fn step<T: Copy + PartialEq>(choices: &[T], current: T, forward: bool) -> T {
+    match choices.iter().position(|c| *c == current) {
+        None => choices[0],
+        Some(i) if forward => choices[(i + 1) % choices.len()],
+        Some(i) => choices[(i + choices.len() - 1) % choices.len()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Settings, Setting, WINDOW_SIZES};
+
+    #[test]
+    fn missing_settings_are_defaults() {
+        let settings: Settings = serde_json::from_str(r#"{ "permadeath": false }"#).unwrap();
+        assert!(!settings.permadeath);
+        assert!(settings.window_size == WINDOW_SIZES[0]);
+        assert!(settings.auto_move.stop_on_sighting);
+    }
+
+    #[test]
+    fn changes_go_round() {
+        let mut settings = Settings::default();
+        settings.change(Setting::WindowSize, false);
+        assert!(settings.window_size == WINDOW_SIZES[WINDOW_SIZES.len() - 1]);
+        settings.change(Setting::WindowSize, true);
+        assert!(settings.window_size == WINDOW_SIZES[0]);
+        settings.change(Setting::ParticleTime, true);
+        assert_eq!(settings.value_text(Setting::ParticleTime), "200%");
+    }
+}
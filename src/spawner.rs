@@ -1,153 +1,248 @@
-use rltk::{RandomNumberGenerator, Point};
+use rltk::Point;
+use crate::random::RandomNumberGenerator;
 use specs::prelude::*;
 use std::collections::{HashSet};
+use serde::{Deserialize};
 use super::map::{MAPWIDTH};
 use super::rect::{Rect};
-use super::stuff::Stuff;
+use super::stuff::{self, StuffDefs};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub const SPAWNS_FILE_PATH: &str = "data/spawns.json";
+
+/// What gets spawned on each floor. Every table whose depths include the floor is used, in order.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnTables {
+    // Used for the room the player starts in, instead of the floor tables.
+    pub start_room: Vec<SpawnTable>,
+    pub floor: Vec<SpawnTable>
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnTable {
+    pub min_depth: i32,
+    // No maximum means every floor from min_depth down.
+    pub max_depth: Option<i32>,
+    pub spawns: Vec<SpawnEntry>,
+    // How many extra things to spawn, picked from the entries by weight.
+    pub weighted: Option<SpawnCount>
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnEntry {
+    pub name: String,
+    #[serde(default)]
+    pub min: i32,
+    #[serde(default)]
+    pub max: i32,
+    #[serde(default)]
+    pub weight: i32
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SpawnCount {
+    pub min: i32,
+    pub max: i32
+}
+
+impl SpawnTables {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(stuff: &StuffDefs) -> Result<Self, String> {
+        let data = std::fs::read_to_string(SPAWNS_FILE_PATH).map_err(|e| format!("can't read {}: {}", SPAWNS_FILE_PATH, e))?;
+        SpawnTables::parse(&data, stuff).map_err(|e| format!("{}: {}", SPAWNS_FILE_PATH, e))
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load(stuff: &StuffDefs) -> Result<Self, String> {
+        SpawnTables::parse(include_str!("../data/spawns.json"), stuff)
+    }
+
+    pub fn parse(data: &str, stuff: &StuffDefs) -> Result<Self, String> {
+        let tables: SpawnTables = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        for (i, table) in tables.start_room.iter().enumerate() {
+            table.validate(stuff).map_err(|e| format!("start room table {}: {}", i + 1, e))?;
+        }
+        for (i, table) in tables.floor.iter().enumerate() {
+            table.validate(stuff).map_err(|e| format!("floor table {}: {}", i + 1, e))?;
+        }
+        Ok(tables)
+    }
+
+    fn for_depth(tables: &[SpawnTable], depth: i32) -> Vec<SpawnTable> {
+        tables.iter().filter(|t| t.includes(depth)).cloned().collect()
+    }
+}
+
+impl SpawnTable {
+    fn includes(&self, depth: i32) -> bool {
+        depth >= self.min_depth && !matches!(self.max_depth, Some(max_depth) if depth > max_depth)
+    }
+
+    fn total_weight(&self) -> i32 {
+        self.spawns.iter().map(|s| s.weight).sum()
+    }
+
+    fn validate(&self, stuff: &StuffDefs) -> Result<(), String> {
+        if let Some(max_depth) = self.max_depth {
+            if max_depth < self.min_depth {
+                return Err(format!("max_depth {} is less than min_depth {}", max_depth, self.min_depth));
+            }
+        }
+        for entry in self.spawns.iter() {
+            if stuff.get(&entry.name).is_none() {
+                return Err(format!("no stuff called {}", entry.name));
+            }
+            if entry.min < 0 || entry.max < entry.min {
+                return Err(format!("{} has bad counts {} to {}", entry.name, entry.min, entry.max));
+            }
+            if entry.weight < 0 {
+                return Err(format!("{} has negative weight {}", entry.name, entry.weight));
+            }
+        }
+        if let Some(weighted) = &self.weighted {
+            if weighted.min < 0 || weighted.max < weighted.min {
+                return Err(format!("bad weighted counts {} to {}", weighted.min, weighted.max));
+            }
+            if self.total_weight() == 0 {
+                return Err("weighted spawns but no entries have a weight".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn pick_weighted(&self, rng: &mut RandomNumberGenerator) -> &str {
+        let mut roll = rng.range(0, self.total_weight());
+        for entry in self.spawns.iter() {
+            if roll < entry.weight {
+                return &entry.name;
+            }
+            roll -= entry.weight;
+        }
+        unreachable!("Roll is below the total weight")
+    }
+}
 
 pub fn spawn(ecs: &mut World, rooms: &Vec<Rect>, map_depth: i32) {
     let start_rooms = rooms[0..1].to_vec();
     let other_rooms = rooms[1..].to_vec();
-    spawn_rooms(ecs, start_rooms, start_room_table(map_depth));
-    spawn_rooms(ecs, other_rooms, floor_table(map_depth));
+    let (start_room_tables, floor_tables) = {
+        let tables = ecs.fetch::<SpawnTables>();
+        (SpawnTables::for_depth(&tables.start_room, map_depth), SpawnTables::for_depth(&tables.floor, map_depth))
+    };
+    spawn_rooms(ecs, start_rooms, start_room_tables);
+    spawn_rooms(ecs, other_rooms, floor_tables);
 }
 
-fn spawn_rooms(ecs: &mut World, rooms: Vec<Rect>, spawn_table: Vec<(Stuff, i32, i32)>) {
-    let mut to_spawn: Vec<(Stuff, Point)> = Vec::new();
-    let mut spawn_points: HashSet<usize> = HashSet::new();
-
-    {
+fn spawn_rooms(ecs: &mut World, rooms: Vec<Rect>, spawn_tables: Vec<SpawnTable>) {
+    let to_spawn = {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        pick_spawns(&mut rng, &rooms, &spawn_tables)
+    };
+
+    for (name, point) in to_spawn {
+        stuff::spawn(ecs, &name, point.x, point.y);
+    }
+}
+
+// What to spawn where in the rooms.
+fn pick_spawns(rng: &mut RandomNumberGenerator, rooms: &[Rect], spawn_tables: &[SpawnTable]) -> Vec<(String, Point)> {
+    let mut to_spawn: Vec<(String, Point)> = Vec::new();
+    let mut spawn_points: HashSet<usize> = HashSet::new();
 
-        for (stuff, min_num, max_num) in spawn_table {
-            let num = rng.range(min_num, max_num + 1);
+    for table in spawn_tables.iter() {
+        for entry in table.spawns.iter() {
+            let num = rng.range(entry.min, entry.max + 1);
             for _ in 0..num {
-                let mut tries = 0;
-                loop {
-                    let room_idx = rng.range(0, rooms.len());
-                    let room = &rooms[room_idx];
-                    let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
-                    let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
-                    let idx = (y * MAPWIDTH) + x;
-                    if !spawn_points.contains(&idx) || tries >= 10 {
-                        to_spawn.push((stuff.clone(), Point::new(x, y)));
-                        break;
-                    }
-                    spawn_points.insert(idx);
-                    tries += 1;
-                }
+                let point = spawn_point(rng, rooms, &mut spawn_points);
+                to_spawn.push((entry.name.clone(), point));
+            }
+        }
+        if let Some(weighted) = &table.weighted {
+            let num = rng.range(weighted.min, weighted.max + 1);
+            for _ in 0..num {
+                let name = table.pick_weighted(rng).to_string();
+                let point = spawn_point(rng, rooms, &mut spawn_points);
+                to_spawn.push((name, point));
             }
         }
     }
+    to_spawn
+}
 
-    for (stuff, point) in to_spawn {
-        stuff.spawn(ecs, point.x, point.y);
+// Picks a free tile in one of the rooms, only doubling up if it can't find one.
+fn spawn_point(rng: &mut RandomNumberGenerator, rooms: &[Rect], spawn_points: &mut HashSet<usize>) -> Point {
+    let mut tries = 0;
+    loop {
+        let room_idx = rng.range(0, rooms.len());
+        let room = &rooms[room_idx];
+        let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
+        let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
+        let idx = (y * MAPWIDTH) + x;
+        if spawn_points.insert(idx) || tries >= 10 {
+            return Point::new(x, y);
+        }
+        tries += 1;
     }
 }
 
-fn floor_table(map_depth: i32) -> Vec<(Stuff, i32, i32)> {
-    match map_depth {
-        1 =>
-            vec![
-                (Stuff::Rabbit, 15, 30),
-                (Stuff::BigRabbit, 5, 10),
-                (Stuff::Thrall, 1, 5),
-                (Stuff::ToughThrall, 0, 1),
-                (Stuff::StunDart, 1, 1),
-                (Stuff::Dart, 1, 1),
-                (Stuff::WaterDart, 2, 3),
-                (Stuff::WaterBalloon, 1, 2),
-                (Stuff::OilDart, 0, 1),
-                (Stuff::OilBalloon, 0, 1),
-                (Stuff::Coffee, 1, 2),
-                (Stuff::StrongCoffee, 0, 1),
-                (Stuff::HealthKit, 1, 2),
-                (Stuff::Firecracker, 2, 3),
-                (Stuff::Flashbang, 1, 2),
-                (Stuff::Knife, 1, 2),
-                (Stuff::LightArmour, 1, 2),
-            ],
-        2 =>
-            vec![
-                (Stuff::Rabbit, 5, 10),
-                (Stuff::BigRabbit, 5, 10),
-                (Stuff::Thrall, 10, 20),
-                (Stuff::ToughThrall, 5, 10),
-                (Stuff::StunDart, 1, 2),
-                (Stuff::LongStunDart, 1, 1),
-                (Stuff::Dart, 1, 1),
-                (Stuff::WaterDart, 1, 3),
-                (Stuff::WaterBalloon, 1, 2),
-                (Stuff::OilDart, 2, 3),
-                (Stuff::OilBalloon, 1, 2),
-                (Stuff::BloodDart, 0, 1),
-                (Stuff::BloodBalloon, 0, 1),
-                (Stuff::Grenade, 0, 1),
-                (Stuff::Coffee, 1, 2),
-                (Stuff::StrongCoffee, 0, 1),
-                (Stuff::HealthKit, 1, 2),
-                (Stuff::SuperHealthKit, 0, 1),
-                (Stuff::Firecracker, 2, 3),
-                (Stuff::Flashbang, 1, 2),
-                (Stuff::Sword, 1, 2),
-                (Stuff::MediumArmour, 1, 2),
-                (Stuff::Shield, 0, 1),
-            ],
-        3 =>
-            vec![
-                (Stuff::Rabbit, 1, 3),
-                (Stuff::BigRabbit, 1, 3),
-                (Stuff::Thrall, 5, 10),
-                (Stuff::ToughThrall, 2, 5),
-                (Stuff::Vampire, 10, 20),
-                (Stuff::OldVampire, 5, 10),
-                (Stuff::StunDart, 1, 2),
-                (Stuff::LongStunDart, 1, 2),
-                (Stuff::Dart, 1, 1),
-                (Stuff::WaterDart, 1, 3),
-                (Stuff::WaterBalloon, 1, 2),
-                (Stuff::OilDart, 1, 3),
-                (Stuff::OilBalloon, 1, 2),
-                (Stuff::BloodDart, 2, 3),
-                (Stuff::BloodBalloon, 1, 2),
-                (Stuff::Grenade, 1, 1),
-                (Stuff::Coffee, 1, 2),
-                (Stuff::StrongCoffee, 1, 2),
-                (Stuff::HealthKit, 1, 2),
-                (Stuff::SuperHealthKit, 1, 2),
-                (Stuff::Firecracker, 2, 3),
-                (Stuff::Flashbang, 1, 2),
-                (Stuff::ElectroSword, 1, 2),
-                (Stuff::HeavyArmour, 1, 2),
-                (Stuff::Shield, 1, 2),
-                (Stuff::SuperSword, 0, 1),
-            ],
-        _ =>
-            vec![
-            ],
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use crate::{Map, random::RandomNumberGenerator, stuff::StuffDefs};
+    use super::{SpawnTables, pick_spawns};
+
+    fn parse_floor(table: &str) -> Result<SpawnTables, String> {
+        SpawnTables::parse(&format!(r#"{{ "start_room": [], "floor": [{}] }}"#, table), &StuffDefs::load().unwrap())
     }
-}
 
-fn start_room_table(map_depth: i32) -> Vec<(Stuff, i32, i32)> {
-    match map_depth {
-        1 =>
-            vec![
-                (Stuff::WaterDart, 1, 1),
-                (Stuff::WaterBalloon, 1, 1),
-                (Stuff::Knife, 1, 1),
-                (Stuff::Firecracker, 1, 1),
-                (Stuff::Coffee, 1, 1),
-            ],
-        2 =>
-            vec![
-                (Stuff::Coffee, 1, 1),
-            ],
-        3 =>
-            vec![
-                (Stuff::Coffee, 1, 1),
-            ],
-        _ =>
-            vec![
-            ],
+    #[test]
+    fn picks_in_proportion_to_weight() {
+        let tables = parse_floor(r#"{ "min_depth": 1, "spawns": [
+            { "name": "rabbit", "weight": 3 },
+            { "name": "thrall", "min": 1, "max": 1 },
+            { "name": "coffee", "weight": 1 }
+        ], "weighted": { "min": 1, "max": 1 } }"#).unwrap();
+        let table = &tables.floor[0];
+        let mut rng = RandomNumberGenerator::seeded(1);
+        let picks: Vec<&str> = (0..4000).map(|_| table.pick_weighted(&mut rng)).collect();
+        let rabbits = picks.iter().filter(|name| **name == "rabbit").count();
+        let coffees = picks.iter().filter(|name| **name == "coffee").count();
+        assert_eq!(rabbits + coffees, 4000);
+        assert!((2850..3150).contains(&rabbits), "{} rabbits", rabbits);
+    }
+
+    #[test]
+    fn rejects_bad_weights() {
+        let negative = parse_floor(r#"{ "min_depth": 1, "spawns": [{ "name": "rabbit", "weight": -1 }] }"#);
+        assert_eq!(negative.err().unwrap(), "floor table 1: rabbit has negative weight -1");
+        let unweighted = parse_floor(r#"{ "min_depth": 1, "spawns": [{ "name": "rabbit", "weight": 0 }], "weighted": { "min": 1, "max": 2 } }"#);
+        assert_eq!(unweighted.err().unwrap(), "floor table 1: weighted spawns but no entries have a weight");
+    }
+
+    #[test]
+    fn checks_depth_ranges() {
+        let backwards = parse_floor(r#"{ "min_depth": 3, "max_depth": 2, "spawns": [] }"#);
+        assert_eq!(backwards.err().unwrap(), "floor table 1: max_depth 2 is less than min_depth 3");
+        let tables = parse_floor(r#"{ "min_depth": 2, "max_depth": 3, "spawns": [] }, { "min_depth": 3, "spawns": [] }"#).unwrap();
+        let counts: Vec<usize> = (1..=5).map(|depth| SpawnTables::for_depth(&tables.floor, depth).len()).collect();
+        assert_eq!(counts, vec![0, 1, 2, 1, 1]);
+    }
+
+    #[test]
+    fn spawns_dont_share_tiles() {
+        let tables = SpawnTables::load(&StuffDefs::load().unwrap()).unwrap();
+        for depth in 1..=3 {
+            let mut rng = RandomNumberGenerator::seeded(7);
+            let map = Map::new(depth, &mut rng);
+            for (rooms, tables) in [(&map.rooms[0..1], &tables.start_room), (&map.rooms[1..], &tables.floor)].iter() {
+                let spawns = pick_spawns(&mut rng, rooms, &SpawnTables::for_depth(tables, depth));
+                let points: HashSet<(i32, i32)> = spawns.iter().map(|(_, p)| (p.x, p.y)).collect();
+                assert_eq!(points.len(), spawns.len(), "spawns share tiles on floor {}", depth);
+            }
+        }
     }
 }
@@ -1,33 +1,224 @@
+use std::time::Instant;
 use specs::prelude::*;
 use rltk::{Point};
-use super::{systems, gamelog, spawner, stuff};
+use super::{systems, gamelog, gui, stats, scores, keymap, settings, events::GameEvents, spawner, stuff, dancing, win_lose_conditions, replay, saveload_system, random::RandomNumberGenerator, RunState, KeyState};
+use super::systems::damage_system::{delete_the_dead};
+use super::systems::initiative;
 use super::map::{Map};
 use super::components::*;
 
 pub struct State {
     pub ecs: World,
-    dispatcher: Box<dyn systems::UnifiedDispatcher + 'static>
+    pub new_game_seed: Seed,
+    pub replay: Option<replay::Replay>,
+    // Whether input is going to the replay file, which stops if it can't be written.
+    pub recording: bool,
+    // Shown on the main menu, like why a save wouldn't load.
+    pub menu_message: Option<String>,
+    pub save_slots: Vec<saveload_system::SaveSlot>,
+    pub settings: settings::Settings,
+    pub log_view: gui::LogView,
+    // Whether to write a morgue file and add to the scores when a game ends, which headless games don't.
+    pub record_runs: bool,
+    // Where the last game's morgue file went, and anything that went wrong recording it.
+    pub game_over_messages: Vec<String>,
+    // Why the scores file couldn't be read, if it couldn't.
+    pub scores: Result<scores::Scores, String>,
+    #[cfg(debug_assertions)]
+    pub console: super::wizard::Console,
+    dispatcher: Box<dyn systems::UnifiedDispatcher + 'static>,
+    profiler: Option<systems::Profiler>
 }
 
 pub type Turn = u32;
 
+pub type Seed = u64;
+
+pub fn random_seed() -> Seed {
+    rltk::RandomNumberGenerator::new().range(0, 1_000_000_000)
+}
+
 impl State {
     pub fn new() -> Self {
         State {
             ecs: World::new(),
-            dispatcher: systems::build()
+            new_game_seed: random_seed(),
+            replay: None,
+            recording: false,
+            menu_message: None,
+            save_slots: Vec::new(),
+            settings: settings::Settings::default(),
+            log_view: gui::LogView::new(),
+            record_runs: false,
+            game_over_messages: Vec::new(),
+            scores: Ok(scores::Scores::default()),
+            #[cfg(debug_assertions)]
+            console: super::wizard::Console::new(),
+            dispatcher: systems::build(None),
+            profiler: None
+        }
+    }
+
+    pub fn setup_resources(&mut self) {
+        setup_ecs(&mut self.ecs);
+        self.ecs.insert(KeyState{ requested_auto_move: false });
+        self.ecs.insert(systems::particle_system::ParticleBuilder::new());
+        self.ecs.insert(systems::auto_movement_system::AutoMoveRules::default());
+        self.ecs.insert(gamelog::PlayerLog::new());
+        let mut events = GameEvents::new();
+        self.ecs.insert(gamelog::GameLog::new(&mut events));
+        self.ecs.insert(stats::Stats::new(&mut events));
+        self.ecs.insert(events);
+    }
+
+    pub fn load_data(&mut self) -> Result<(), String> {
+        let stuff = stuff::StuffDefs::load()?;
+        self.ecs.insert(spawner::SpawnTables::load(&stuff)?);
+        self.ecs.insert(dancing::DanceDefs::load(&stuff)?);
+        self.ecs.insert(stuff);
+        self.ecs.insert(keymap::Keymap::load()?);
+        Ok(())
+    }
+
+    /// Says which keys are bound to more than one command, on stderr and the main menu.
+    pub fn report_key_conflicts(&mut self) {
+        let conflicts = &self.ecs.fetch::<keymap::Keymap>().conflicts;
+        for conflict in conflicts.iter() {
+            eprintln!("{}", conflict);
+        }
+        if !conflicts.is_empty() {
+            self.menu_message = Some(format!("Some keys are bound twice: {}.", conflicts.join(", ")));
+        }
+    }
+
+    pub fn refresh_save_slots(&mut self) {
+        if let Err(e) = saveload_system::move_old_save() {
+            self.menu_message = Some(format!("Couldn't move the old save into slot 1: {}.", e));
         }
+        self.save_slots = saveload_system::save_slots();
+    }
+
+    /// Reads the settings file, going with the defaults if it can't be used. A bad file is left
+    /// alone until the settings are next changed.
+    pub fn load_settings(&mut self) {
+        match settings::Settings::load() {
+            Ok(settings) => self.settings = settings,
+            Err(e) => {
+                eprintln!("{}", e);
+                self.menu_message = Some(format!("Using the default settings, since {}.", e));
+            }
+        }
+        self.ecs.fetch_mut::<systems::particle_system::ParticleBuilder>().set_lifetime_percent(self.settings.particle_time);
+    }
+
+    pub fn load_scores(&mut self) {
+        self.scores = scores::Scores::load();
+    }
+
+    /// Times every system from now on, reporting to stderr as it goes.
+    pub fn enable_profiling(&mut self) {
+        let profiler = systems::Profiler::new();
+        self.dispatcher = systems::build(Some(profiler.clone()));
+        self.profiler = Some(profiler);
+    }
+
+    pub fn profile_report(&self) -> Option<String> {
+        self.profiler.as_ref().map(|profiler| profiler.report())
     }
 
     pub fn run_systems(&mut self) {
-        self.dispatcher.run_now(&mut self.ecs);
+        match &self.profiler {
+            None => self.dispatcher.run_now(&mut self.ecs),
+            Some(profiler) => {
+                let start = Instant::now();
+                self.dispatcher.run_now(&mut self.ecs);
+                profiler.finish_dispatch(start.elapsed());
+            }
+        }
         self.ecs.maintain();
     }
 
-    pub fn setup_world(&mut self) {
+    /// Runs the run states that don't need input or a display, giving back the next state.
+    pub fn advance(&mut self, runstate: &RunState) -> RunState {
+        match runstate {
+            RunState::PreRun | RunState::Ticking => {
+                self.run_systems();
+                if initiative::give_turns(&mut self.ecs) {
+                    RunState::AwaitingInput
+                } else {
+                    RunState::Ticking
+                }
+            }
+            RunState::NextLevel => {
+                self.goto_next_level();
+                {
+                    let map = self.ecs.read_resource::<Map>();
+                    let turn = *self.ecs.fetch::<Turn>();
+                    let mut player_log = self.ecs.write_resource::<gamelog::PlayerLog>();
+                    player_log.insert(turn, gamelog::Category::System, &format!("You go down stairs to floor {}.", map.depth));
+                }
+                RunState::PreRun
+            }
+            _ => runstate.clone()
+        }
+    }
+
+    pub fn player_is_busy(&self) -> bool {
+        let player = *self.ecs.fetch::<Entity>();
+        let dancers = self.ecs.read_storage::<Dancing>();
+        dancers.get(player).is_some()
+    }
+
+    pub fn finish_tick(&mut self, newrunstate: RunState) {
+        let was_over = matches!(newrunstate, RunState::GameOver { .. });
+        {
+            let mut runwriter = self.ecs.write_resource::<RunState>();
+            *runwriter = newrunstate;
+        }
+
+        win_lose_conditions::check_lose(&mut self.ecs);
+        delete_the_dead(&mut self.ecs);
+
+        if was_over {
+            return;
+        }
+        let runstate = (*self.ecs.fetch::<RunState>()).clone();
+        if let RunState::GameOver { won, reason } = runstate {
+            self.end_game(won, &reason);
+        }
+    }
+
+    fn end_game(&mut self, won: bool, reason: &str) {
+        // Count the deaths from this last tick, which the systems won't get to now.
+        systems::StatsUpdaterSystem{}.run_now(&self.ecs);
+        self.game_over_messages.clear();
+        if !self.record_runs || self.replay.is_some() {
+            return;
+        }
+        self.game_over_messages.push(match stats::write_morgue(&self.ecs, won, reason) {
+            Ok(path) => format!("Morgue file written to {}.", path),
+            Err(e) => format!("Couldn't write a morgue file: {}", e)
+        });
+        let run = scores::RunRecord::new(
+            *self.ecs.fetch::<Seed>(), won, reason, self.ecs.fetch::<Map>().depth, *self.ecs.fetch::<Turn>(),
+            &self.ecs.fetch::<stats::Stats>().run);
+        match &mut self.scores {
+            Ok(scores) => if let Err(e) = scores.add(run) {
+                self.game_over_messages.push(format!("Couldn't save the score: {}", e));
+            }
+            Err(e) => self.game_over_messages.push(format!("The score wasn't saved, since {}.", e))
+        }
+    }
+
+    pub fn setup_world(&mut self, seed: Seed, auto_move: systems::auto_movement_system::AutoMoveRules) {
+        self.ecs.insert(RandomNumberGenerator::seeded(seed));
+        self.ecs.insert::<Seed>(seed);
+        self.ecs.insert(auto_move);
+        self.ecs.insert(replay::InputRecord::new(seed, auto_move));
+
         let map;
         {
-            let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
+            let mut rng = self.ecs.write_resource::<RandomNumberGenerator>();
             map = Map::new(1, &mut rng);
         }
 
@@ -59,14 +250,12 @@ impl State {
         }
 
         let mut gamelog = self.ecs.write_resource::<gamelog::GameLog>();
-        gamelog.entries.clear();
+        gamelog.clear(&self.ecs.fetch::<GameEvents>());
+        self.ecs.write_resource::<stats::Stats>().clear(&self.ecs.fetch::<GameEvents>());
         let mut player_log = self.ecs.write_resource::<gamelog::PlayerLog>();
-        player_log.entries.clear();
-    }
-
-    pub fn next_turn(&mut self) {
-        let mut turn = self.ecs.write_resource::<Turn>();
-        *turn += 1;
+        player_log.clear();
+        let mut particle_builder = self.ecs.write_resource::<systems::particle_system::ParticleBuilder>();
+        particle_builder.clear();
     }
 
     pub fn goto_next_level(&mut self) {
@@ -78,7 +267,7 @@ impl State {
         let worldmap;
         let current_depth;
         {
-            let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
+            let mut rng = self.ecs.write_resource::<RandomNumberGenerator>();
             let mut worldmap_resource = self.ecs.write_resource::<Map>();
             current_depth = worldmap_resource.depth;
             *worldmap_resource = Map::new(current_depth + 1, &mut rng);
@@ -113,10 +302,11 @@ impl State {
         for del in to_delete.iter() {
             self.ecs.delete_entity(*del).expect("Deletion failed");
         }
+        self.ecs.write_resource::<systems::particle_system::ParticleBuilder>().clear();
 
         let worldmap;
         {
-            let mut rng = self.ecs.write_resource::<rltk::RandomNumberGenerator>();
+            let mut rng = self.ecs.write_resource::<RandomNumberGenerator>();
             let mut worldmap_resource = self.ecs.write_resource::<Map>();
             *worldmap_resource = Map::new(1, &mut rng);
             worldmap = worldmap_resource.clone();
@@ -146,8 +336,8 @@ impl State {
 
     fn intro_log(&mut self) {
         let mut log = self.ecs.write_resource::<gamelog::GameLog>();
-        log.global(&"Welcome to the moon.");
-        log.global(&"Press / for help. Use mouse to look around.");
+        log.global(gamelog::Category::System, &"Welcome to the moon.");
+        log.global(gamelog::Category::System, &"Press / for help. Use mouse to look around.");
     }
 
     fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
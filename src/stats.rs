@@ -0,0 +1,143 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+use specs::prelude::*;
+use specs::shrev::ReaderId;
+use serde::{Serialize, Deserialize};
+use rltk::{Point};
+use super::{events::{GameEvent, GameEvents}, Map, TileType, Turn, Seed, Name, Position, Renderable, InBackpack, Equipped};
+
+/// How the player's run has gone so far. Saved with the game.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct RunStats {
+    pub monsters_killed: u32,
+    pub monsters_scared_off: u32,
+    // Times the player started each dance, by its name.
+    pub dances: BTreeMap<String, u32>,
+    pub poise_inflicted: i32,
+    pub items_used: u32,
+    pub tiles_stained: u32
+}
+
+/// The run's stats, with the reader `StatsUpdaterSystem` uses to keep them up to date.
+pub struct Stats {
+    pub run: RunStats,
+    pub events: ReaderId<GameEvent>
+}
+
+impl Stats {
+    pub fn new(events: &mut GameEvents) -> Self {
+        Stats {
+            run: RunStats::default(),
+            events: events.register_reader()
+        }
+    }
+
+    /// Starts again from nothing, skipping any events not yet read.
+    pub fn clear(&mut self, events: &GameEvents) {
+        self.run = RunStats::default();
+        for _ in events.read(&mut self.events) {}
+    }
+}
+
+impl RunStats {
+    /// One line for each stat, for the game over screen and the morgue file.
+    pub fn lines(&self, turn: Turn) -> Vec<String> {
+        let mut lines = vec![
+            format!("Turns taken: {}", turn),
+            format!("Monsters killed: {}", self.monsters_killed),
+            format!("Monsters scared off: {}", self.monsters_scared_off),
+            format!("Poise inflicted: {}", self.poise_inflicted),
+            format!("Items used: {}", self.items_used),
+            format!("Tiles stained: {}", self.tiles_stained)
+        ];
+        if self.dances.is_empty() {
+            lines.push("Dances: none".to_string());
+        } else {
+            let dances: Vec<String> = self.dances.iter().map(|(name, times)| format!("{} {}", name, times)).collect();
+            lines.push(format!("Dances: {}", dances.join(", ")));
+        }
+        lines
+    }
+}
+
+/// Writes how the game went to a new text file, giving where it went. The player may have died
+/// already, so what they had is found by what they owned.
+pub fn write_morgue(ecs: &World, won: bool, reason: &str) -> io::Result<String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let path = format!("./morgue-{}.txt", now);
+    let mut file = fs::File::create(&path)?;
+    file.write_all(morgue(ecs, won, reason).as_bytes())?;
+    Ok(path)
+}
+
+fn morgue(ecs: &World, won: bool, reason: &str) -> String {
+    let player = *ecs.fetch::<Entity>();
+    let map = ecs.fetch::<Map>();
+    let turn = *ecs.fetch::<Turn>();
+    let mut text = format!("You {}. {}\n", if won { "won" } else { "lost" }, reason);
+    text.push_str(&format!("Seed {}, floor {}, turn {}.\n\n", *ecs.fetch::<Seed>(), map.depth, turn));
+
+    for line in ecs.fetch::<Stats>().run.lines(turn) {
+        text.push_str(&format!("{}\n", line));
+    }
+
+    text.push_str("\nInventory:\n");
+    let names = ecs.read_storage::<Name>();
+    let backpacks = ecs.read_storage::<InBackpack>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let mut items = Vec::new();
+    for (name, _) in (&names, &equipped).join().filter(|(_, e)| e.owner == player) {
+        items.push(format!("  {} (equipped)\n", name.name));
+    }
+    for (name, _) in (&names, &backpacks).join().filter(|(_, b)| b.owner == player) {
+        items.push(format!("  {}\n", name.name));
+    }
+    if items.is_empty() {
+        items.push("  nothing\n".to_string());
+    }
+    text.extend(items);
+
+    text.push_str(&format!("\nFloor {}:\n", map.depth));
+    let rows: Vec<String> = map_rows(ecs, &map).iter().map(|row| row.trim_end().to_string()).collect();
+    // Leave out the rows above and below everything seen.
+    let first = rows.iter().position(|row| !row.is_empty()).unwrap_or(0);
+    let last = rows.iter().rposition(|row| !row.is_empty()).unwrap_or(0);
+    for row in &rows[first..=last] {
+        text.push_str(row);
+        text.push('\n');
+    }
+    text
+}
+
+// The map as the player last saw it, with whatever they could see on it.
+fn map_rows(ecs: &World, map: &Map) -> Vec<String> {
+    let mut chars: Vec<char> = map.tiles.iter().enumerate().map(|(idx, tile)| {
+        if !map.revealed_tiles[idx] {
+            ' '
+        } else {
+            match tile {
+                TileType::Floor => '.',
+                TileType::Wall => '#',
+                TileType::DownStairs => '>'
+            }
+        }
+    }).collect();
+
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
+    data.sort_by_key(|&(_, render)| std::cmp::Reverse(render.render_order));
+    for (pos, render) in data {
+        let idx = map.xy_idx(pos.x, pos.y);
+        if map.visible_tiles[idx] {
+            chars[idx] = rltk::to_char(render.glyph as u8);
+        }
+    }
+    // The player's entity is gone if they died.
+    let player_pos = *ecs.fetch::<Point>();
+    chars[map.point_idx(&player_pos)] = '@';
+
+    chars.chunks(map.width as usize).map(|row| row.iter().collect()).collect()
+}
@@ -1,30 +1,46 @@
 use std::collections::HashSet;
 use specs::prelude::*;
 use rltk::{Point};
-use crate::{Map, Position, MovingAutomatically, Viewshed, RunState, WantsToMove};
+use serde::{Serialize, Deserialize};
+use crate::{Map, Position, MovingAutomatically, Viewshed, MyTurn, WantsToMove};
+
+/// What stops moving automatically, besides running into something. These change how a game
+/// plays out, so each game keeps the ones it started with, in its input record.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AutoMoveRules {
+    // Anything coming into view that hadn't been seen since setting off.
+    pub stop_on_sighting: bool,
+    // More room opening up to either side, like a side passage or a doorway into a room.
+    pub stop_at_openings: bool
+}
+
+impl Default for AutoMoveRules {
+    fn default() -> Self {
+        AutoMoveRules { stop_on_sighting: true, stop_at_openings: true }
+    }
+}
 
 pub struct AutoMovementSystem {}
 
 impl<'a> System<'a> for AutoMovementSystem {
     type SystemData = (Entities<'a>,
+                       ReadExpect<'a, AutoMoveRules>,
                        ReadExpect<'a, Map>,
-                       ReadExpect<'a, RunState>,
+                       ReadStorage<'a, MyTurn>,
                        ReadStorage<'a, Position>,
                        WriteStorage<'a, MovingAutomatically>,
                        ReadStorage<'a, Viewshed>,
                        WriteStorage<'a, WantsToMove>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, map, runstate, positions, mut auto_moving, viewsheds, mut wants_to_moves) = data;
-
-        if *runstate != RunState::MonsterTurn { return; }
+        let (entities, rules, map, my_turns, positions, mut auto_moving, viewsheds, mut wants_to_moves) = data;
 
         let mut to_remove: Vec<Entity> = Vec::new();
-        for (entity, pos, mut auto_move, viewshed) in (&entities, &positions, &mut auto_moving, &viewsheds).join() {
+        for (entity, pos, mut auto_move, viewshed, _my_turn) in (&entities, &positions, &mut auto_moving, &viewsheds, &my_turns).join() {
             let (x, y) = (pos.x + auto_move.direction.x, pos.y + auto_move.direction.y);
             let saw_new = update_seen(&mut auto_move, &viewshed, &map);
             let clearance_grew = update_left_right(&mut auto_move, &Point::new(pos.x, pos.y), &map);
-            if saw_new || clearance_grew || x < 0 || x >= map.width || y < 0 || y >= map.height || map.blocked[map.xy_idx(x, y)] {
+            if (saw_new && rules.stop_on_sighting) || (clearance_grew && rules.stop_at_openings) || x < 0 || x >= map.width || y < 0 || y >= map.height || map.blocked[map.xy_idx(x, y)] {
                 to_remove.push(entity);
             } else {
                 wants_to_moves.insert(entity, WantsToMove {
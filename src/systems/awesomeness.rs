@@ -1,37 +1,38 @@
 use std::cmp::{max};
 use specs::prelude::*;
-use crate::{gamelog::GameLog, text::capitalize, Poise, Awestruck, systems::particle_system::ParticleBuilder, Position, Name};
+use crate::{events::{GameEvents, GameEvent}, Poise, Awestruck, AwedBy, systems::particle_system::ParticleBuilder, Position};
 
 pub struct AwesomenessSystem {}
 
 impl<'a> System<'a> for AwesomenessSystem {
     type SystemData = (
         Entities<'a>,
-        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, GameEvents>,
         WriteStorage<'a, Poise>,
         WriteStorage<'a, Awestruck>,
         WriteExpect<'a, ParticleBuilder>,
         ReadStorage<'a, Position>,
-        ReadStorage<'a, Name>
+        WriteStorage<'a, AwedBy>
     );
 
     fn run(&mut self, data : Self::SystemData) {
         let (
             entities,
-            mut gamelog,
+            mut events,
             mut poise,
             mut awestruck,
             mut particle_builder,
             positions,
-            names
+            mut awed_by
         ) = data;
 
-        for (entity, mut poise, awestruck, name) in (&entities, &mut poise, &awestruck, &names).join() {
-            gamelog.on(entity, &format!("{} {} awed by {} ({} {}).", capitalize(&name.np), name.verb("is", "are"), awestruck.reason, awestruck.poise, Poise::NAME));
+        for (entity, mut poise, awestruck) in (&entities, &mut poise, &awestruck).join() {
+            events.single_write(GameEvent::Awed { entity, by: awestruck.by, reason: awestruck.reason.clone(), poise: awestruck.poise });
             if let Some(pos) = positions.get(entity) {
                 particle_builder.request(pos.x, pos.y, rltk::RGB::named(rltk::ORANGE), rltk::to_cp437('!'), 200.0);
             }
             poise.poise = max(0, poise.poise - awestruck.poise);
+            awed_by.insert(entity, AwedBy { by: awestruck.by }).expect("Unable to insert awed by");
         }
 
         awestruck.clear();
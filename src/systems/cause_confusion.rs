@@ -1,30 +1,27 @@
 use specs::prelude::*;
-use crate::{gamelog::GameLog, text::capitalize, ItemUseInProgress, CausesConfusion, Confusion, Name};
+use crate::{events::{GameEvents, GameEvent}, ItemUseInProgress, CausesConfusion, Confusion};
 
 pub struct CauseConfusionSystem {}
 
 impl<'a> System<'a> for CauseConfusionSystem {
     type SystemData = (
-        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, GameEvents>,
         Entities<'a>,
         WriteStorage<'a, ItemUseInProgress>,
         ReadStorage<'a, CausesConfusion>,
-        WriteStorage<'a, Confusion>,
-        ReadStorage<'a, Name>
+        WriteStorage<'a, Confusion>
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut gamelog, entities, use_in_progress, causes_confusion, mut confused, names) = data;
+        let (mut events, entities, use_in_progress, causes_confusion, mut confused) = data;
 
-        for (entity, useitem, name) in (&entities, &use_in_progress, &names).join() {
+        for (entity, useitem) in (&entities, &use_in_progress).join() {
             let mut add_confusion = Vec::new();
             {
                 if let Some(confusion) = causes_confusion.get(useitem.item) {
                     for mob in useitem.targets.iter() {
                         add_confusion.push((*mob, confusion.turns));
-                        let mob_name = names.get(*mob).unwrap();
-                        let item_name = names.get(useitem.item).unwrap();
-                        gamelog.on(entity, &format!("{} {} {} on {}, confusing {}.", capitalize(&name.np), name.verb("uses", "use"), item_name.np, mob_name.np, mob_name.pronoun));
+                        events.single_write(GameEvent::Confused { user: entity, item: useitem.item, target: *mob });
                     }
                 }
             }
@@ -1,43 +1,29 @@
 use specs::prelude::*;
-use crate::{RunState, Confusion, Player, Monster, systems::particle_system::ParticleBuilder, Position};
+use crate::{MyTurn, Confusion, systems::particle_system::ParticleBuilder, Position};
 
 pub struct ConfusionSystem {}
 
 impl<'a> System<'a> for ConfusionSystem {
     type SystemData = (
-        ReadExpect<'a, RunState>,
         WriteExpect<'a, ParticleBuilder>,
         Entities<'a>,
-        ReadStorage<'a, Player>,
-        ReadStorage<'a, Monster>,
+        ReadStorage<'a, MyTurn>,
         ReadStorage<'a, Position>,
         WriteStorage<'a, Confusion>
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            runstate,
             mut particle_builder,
             entities,
-            players,
-            monsters,
+            my_turns,
             positions,
             mut confusion,
         ) = data;
 
         let mut to_remove = Vec::new();
 
-        for (entity, pos, mut confused) in (&entities, &positions, &mut confusion).join() {
-            if *runstate == RunState::PlayerTurn {
-                if let None = players.get(entity) {
-                    continue;
-                }
-            } else {
-                if let None = monsters.get(entity) {
-                    continue;
-                }
-            }
-
+        for (entity, pos, mut confused, _my_turn) in (&entities, &positions, &mut confusion, &my_turns).join() {
             confused.turns -= 1;
             if confused.turns < 1 {
                 to_remove.push(entity);
@@ -1,6 +1,6 @@
 use specs::prelude::*;
 use rltk::{Point};
-use crate::{Health, SufferDamage, Name, gamelog::GameLog, text::capitalize, Position, Map, liquids::Liquid, Stamina};
+use crate::{Health, SufferDamage, HurtBy, Name, events::{GameEvents, GameEvent}, Position, Map, liquids::Liquid, Stamina};
 
 pub struct DamageSystem {}
 
@@ -34,13 +34,14 @@ pub fn delete_the_dead(ecs: &mut World) {
         let health = ecs.read_storage::<Health>();
         let names = ecs.read_storage::<Name>();
         let positions = ecs.read_storage::<Position>();
+        let hurt_by = ecs.read_storage::<HurtBy>();
         let entities = ecs.entities();
-        let mut gamelog = ecs.write_resource::<GameLog>();
+        let mut events = ecs.write_resource::<GameEvents>();
         for (entity, health) in (&entities, &health).join() {
             if health.health < 1 {
                 if let Some(victim_name) = names.get(entity) {
                     if let Some(pos) = positions.get(entity) {
-                        gamelog.at(Point::new(pos.x, pos.y), &format!("{} {} dead", capitalize(&victim_name.np), victim_name.verb("is", "are")));
+                        events.single_write(GameEvent::Died { entity, name: victim_name.clone(), at: Point::new(pos.x, pos.y), by: hurt_by.get(entity).map(|h| h.by) });
                     }
                 }
                 dead.push(entity)
@@ -1,17 +1,17 @@
 use std::collections::HashSet;
 use specs::prelude::*;
-use rltk::{Point, RandomNumberGenerator};
-use crate::{Position, WantsToDance, Name, Dancing, gamelog::GameLog, text::capitalize, Map, RunState, Player, Monster, systems::particle_system::ParticleBuilder, EffectRequest, WantsToMove, Poise, CanDoDances, Stamina, dancing::Dance, Confusion};
+use rltk::Point;
+use crate::random::RandomNumberGenerator;
+use crate::{Position, WantsToDance, Name, Dancing, events::{GameEvents, GameEvent, Hindrance}, Map, MyTurn, systems::particle_system::ParticleBuilder, EffectRequest, WantsToMove, Poise, CanDoDances, Stamina, dancing::Dance, Confusion, systems::initiative::DANCE_STEP_COST};
 
 pub struct StartDancingSystem {}
 
 impl<'a> System<'a> for StartDancingSystem {
     type SystemData = (
-        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, GameEvents>,
         Entities<'a>,
         ReadStorage<'a, Position>,
         WriteStorage<'a, WantsToDance>,
-        ReadStorage<'a, Name>,
         WriteStorage<'a, Dancing>,
         ReadStorage<'a, Stamina>,
         ReadStorage<'a, Poise>,
@@ -20,41 +20,40 @@ impl<'a> System<'a> for StartDancingSystem {
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            mut gamelog,
+            mut events,
             entities,
             positions,
             mut want_to_dancers,
-            names,
             mut dancers,
             stamina,
             poise,
             confusion
         ) = data;
 
-        for (entity, pos, want_dance, name) in (&entities, &positions, &want_to_dancers, &names).join() {
+        for (entity, pos, want_dance) in (&entities, &positions, &want_to_dancers).join() {
             if let Some(_) = confusion.get(entity) {
-                gamelog.on(entity, &format!("{} {} too confused to dance.", capitalize(&name.np), name.verb("is", "are")));
+                events.single_write(GameEvent::CouldntDance { entity, hindrance: Hindrance::Confused });
                 continue;
             }
             if let Some(stamina) = stamina.get(entity) {
                 if stamina.stamina <= 0 {
-                    gamelog.on(entity, &format!("{} {} too tired to dance.", capitalize(&name.np), name.verb("is", "are")));
+                    events.single_write(GameEvent::CouldntDance { entity, hindrance: Hindrance::Tired });
                     continue;
                 }
             }
             if let Some(poise) = poise.get(entity) {
                 if poise.poise <= 0 {
-                    gamelog.on(entity, &format!("{} {} too intimidated to dance.", capitalize(&name.np), name.verb("is", "are")));
+                    events.single_write(GameEvent::CouldntDance { entity, hindrance: Hindrance::Intimidated });
                     continue;
                 }
             }
 
-            gamelog.on(entity, &format!("{} {} the {} dance.", capitalize(&name.np), name.verb("starts", "start"), want_dance.dance.name()));
+            events.single_write(GameEvent::StartedDance { entity, dance: want_dance.dance.clone() });
             dancers.insert(entity, Dancing {
                 dance: want_dance.dance.clone(),
                 range: get_dance_range(&want_dance.dance, &Point::new(pos.x, pos.y)),
                 expect_pos: Point::new(pos.x, pos.y),
-                steps: want_dance.dance.steps(),
+                steps: want_dance.dance.steps.clone(),
                 step_idx: 0,
                 repetitions: want_dance.repetitions
             }).expect("Failed to insert dancing.");
@@ -68,13 +67,11 @@ pub struct DancingMovementSystem {}
 
 impl<'a> System<'a> for DancingMovementSystem {
     type SystemData = (ReadExpect<'a, Map>,
-                       ReadExpect<'a, RunState>,
                        WriteExpect<'a, RandomNumberGenerator>,
-                       WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, GameEvents>,
                        Entities<'a>,
                        ReadStorage<'a, Position>,
-                       ReadStorage<'a, Player>,
-                       ReadStorage<'a, Monster>,
+                       WriteStorage<'a, MyTurn>,
                        WriteExpect<'a, ParticleBuilder>,
                        WriteStorage<'a, Dancing>,
                        WriteStorage<'a, EffectRequest>,
@@ -86,38 +83,29 @@ impl<'a> System<'a> for DancingMovementSystem {
                        WriteStorage<'a, Confusion>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (map, runstate, mut rng, mut gamelog, entities, pos, players, monsters, mut particle_builder, mut dancers, mut effect_requests, mut wants_to_moves, names, can_do_dances, mut stamina, mut poise, confusion) = data;
+        let (map, mut rng, mut events, entities, pos, mut my_turns, mut particle_builder, mut dancers, mut effect_requests, mut wants_to_moves, names, can_do_dances, mut stamina, mut poise, confusion) = data;
 
         let mut to_stop: Vec<Entity> = Vec::new();
-        for (entity, pos, mut dancer, mut stamina, name, mut poise) in (&entities, &pos, &mut dancers, &mut stamina, &names, &mut poise).join() {
-            if *runstate == RunState::PlayerTurn {
-                if let None = players.get(entity) {
-                    continue;
-                }
-            } else {
-                if let None = monsters.get(entity) {
-                    continue;
-                }
-            }
-
+        for (entity, pos, mut dancer, mut stamina, mut poise, my_turn) in (&entities, &pos, &mut dancers, &mut stamina, &mut poise, &mut my_turns).join() {
             if let Some(_) = confusion.get(entity) {
-                gamelog.on(entity, &format!("{} {} too confused to continue dancing (1 {}).", capitalize(&name.np), name.verb("is", "are"), Poise::NAME));
+                events.single_write(GameEvent::StoppedDancing { entity, hindrance: Hindrance::Confused, poise: 1 });
                 poise.poise = i32::max(0, poise.poise - 1);
                 to_stop.push(entity);
                 continue;
             }
             if stamina.stamina <= 0 {
-                gamelog.on(entity, &format!("{} {} too tired to continue dancing (1 {}).", capitalize(&name.np), name.verb("is", "are"), Poise::NAME));
+                events.single_write(GameEvent::StoppedDancing { entity, hindrance: Hindrance::Tired, poise: 1 });
                 particle_builder.request(pos.x, pos.y, rltk::RGB::named(rltk::MAGENTA), rltk::to_cp437('?'), 200.0);
                 poise.poise = i32::max(0, poise.poise - 1);
                 to_stop.push(entity);
                 continue;
             } else {
-                stamina.stamina -= 1;
+                stamina.stamina -= dancer.dance.stamina_per_step;
             }
 
             let step = &dancer.steps[dancer.step_idx as usize];
             dancer.step_idx += 1;
+            my_turn.cost = DANCE_STEP_COST;
 
             let new_x = pos.x + step.direction.x;
             let new_y = pos.y + step.direction.y;
@@ -154,21 +142,20 @@ impl<'a> System<'a> for DancingMovementSystem {
 pub struct DancingStatusSystem {}
 
 impl<'a> System<'a> for DancingStatusSystem {
-    type SystemData = (WriteExpect<'a, GameLog>,
+    type SystemData = (WriteExpect<'a, GameEvents>,
                        Entities<'a>,
                        ReadStorage<'a, Position>,
                        WriteExpect<'a, ParticleBuilder>,
                        WriteStorage<'a, Dancing>,
-                       ReadStorage<'a, Name>,
                        WriteStorage<'a, Poise>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut gamelog, entities, pos, mut particle_builder, mut dancers, names, mut poise) = data;
+        let (mut events, entities, pos, mut particle_builder, mut dancers, mut poise) = data;
 
         let mut to_stop: Vec<Entity> = Vec::new();
-        for (entity, pos, mut dancer, name, mut poise) in (&entities, &pos, &mut dancers, &names, &mut poise).join() {
+        for (entity, pos, mut dancer, mut poise) in (&entities, &pos, &mut dancers, &mut poise).join() {
             if pos.x != dancer.expect_pos.x || pos.y != dancer.expect_pos.y {
-                gamelog.on(entity, &format!("{} {} {} dance (1 {}).", capitalize(&name.np), name.verb("fails", "fail"), name.pronoun_pos, Poise::NAME));
+                events.single_write(GameEvent::FailedDance { entity, poise: 1 });
                 particle_builder.request(pos.x, pos.y, rltk::RGB::named(rltk::MAGENTA), rltk::to_cp437('?'), 200.0);
                 poise.poise = i32::max(0, poise.poise - 1);
                 to_stop.push(entity);
@@ -180,7 +167,7 @@ impl<'a> System<'a> for DancingStatusSystem {
                 dancer.repetitions -= 1;
             }
             if dancer.repetitions <= 0 {
-                gamelog.on(entity, &format!("{} {} dancing.", capitalize(&name.np), name.verb("finishes", "finish")));
+                events.single_write(GameEvent::FinishedDance { entity });
                 to_stop.push(entity);
                 continue;
             }
@@ -196,9 +183,84 @@ fn get_dance_range(dance: &Dance, start: &Point) -> HashSet<Point> {
     let mut range = HashSet::new();
     let mut at = *start;
     range.insert(at);
-    for step in dance.steps().iter() {
+    for step in dance.steps.iter() {
         at = at + step.direction;
         range.insert(at);
     }
     return range;
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use specs::prelude::*;
+    use rltk::Point;
+    use crate::testing::Fixture;
+    use crate::dancing::{Dance, Step};
+    use crate::{Dancing, Poise};
+    use super::DancingStatusSystem;
+
+    // A dance of two steps, right then left, part way through.
+    fn dancing(expect_pos: Point, step_idx: u32, repetitions: u32) -> Dancing {
+        let steps = vec![
+            Step { direction: Point::new(1, 0), effect: None },
+            Step { direction: Point::new(-1, 0), effect: None }
+        ];
+        Dancing {
+            dance: Dance { name: "shuffle".to_string(), steps: steps.clone(), stamina_per_step: 1 },
+            range: HashSet::new(),
+            expect_pos,
+            steps,
+            step_idx,
+            repetitions
+        }
+    }
+
+    #[test]
+    fn pushed_out_of_step_fails() {
+        let mut fixture = Fixture::new("
+            #####
+            #@..#
+            #####");
+        let player = fixture.player();
+        fixture.insert(player, dancing(Point::new(2, 1), 1, 1));
+
+        fixture.run(DancingStatusSystem{});
+
+        assert!(fixture.get::<Dancing>(player).is_none());
+        assert_eq!(fixture.get::<Poise>(player).unwrap().poise, 9);
+        assert_eq!(fixture.log(), vec!["You fail your dance (1 poise)."]);
+    }
+
+    #[test]
+    fn finishes_after_last_repetition() {
+        let mut fixture = Fixture::new("
+            #####
+            #@..#
+            #####");
+        let player = fixture.player();
+        fixture.insert(player, dancing(Point::new(1, 1), 2, 1));
+
+        fixture.run(DancingStatusSystem{});
+
+        assert!(fixture.get::<Dancing>(player).is_none());
+        assert_eq!(fixture.get::<Poise>(player).unwrap().poise, 10);
+        assert_eq!(fixture.log(), vec!["You finish dancing."]);
+    }
+
+    #[test]
+    fn starts_next_repetition() {
+        let mut fixture = Fixture::new("
+            #####
+            #@..#
+            #####");
+        let player = fixture.player();
+        fixture.insert(player, dancing(Point::new(1, 1), 2, 2));
+
+        fixture.run(DancingStatusSystem{});
+
+        let dancing = fixture.ecs.read_storage::<Dancing>().get(player).map(|d| (d.step_idx, d.repetitions));
+        assert_eq!(dancing, Some((0, 1)));
+        assert!(fixture.log().is_empty());
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+use specs::prelude::*;
+use specs::storage::MaskedStorage;
+
+/// A system as declared in `construct_dispatcher!`: its name, the systems whose results it
+/// reads, and what it reads and writes.
+pub struct Node {
+    name: &'static str,
+    deps: &'static [&'static str],
+    reads: Vec<ResourceId>,
+    writes: Vec<ResourceId>
+}
+
+impl Node {
+    pub fn new<S: for<'c> System<'c>>(system: &S, name: &'static str, deps: &'static [&'static str]) -> Self {
+        let accessor = <S as System<'static>>::accessor(system);
+        Node { name, deps, reads: accessor.reads(), writes: accessor.writes() }
+    }
+}
+
+/// A component that asks for something to be done, with the systems that do it and any that
+/// clear it away afterwards.
+pub struct Intent {
+    name: &'static str,
+    storage: ResourceId,
+    consumers: &'static [&'static str],
+    cleanup: &'static [&'static str]
+}
+
+impl Intent {
+    pub fn new<C: Component>(name: &'static str, consumers: &'static [&'static str], cleanup: &'static [&'static str]) -> Self {
+        Intent { name, storage: ResourceId::new::<MaskedStorage<C>>(), consumers, cleanup }
+    }
+}
+
+/// Checks the declared systems hang together, and gives the order to add them in, which is
+/// the order they run in with the single-threaded dispatcher. Where the dependencies leave a
+/// choice, systems keep the order they're declared in.
+///
+/// Any other system that writes an intent has to run before everything consuming it, and
+/// cleaning up has to wait for all of them. Getting the declarations wrong is a bug, so it
+/// panics rather than building a dispatcher that runs things in the wrong order.
+pub fn order(nodes: &[Node], intents: &[Intent]) -> Vec<usize> {
+    let index = |name: &str| nodes.iter().position(|node| node.name == name);
+    for (i, node) in nodes.iter().enumerate() {
+        if index(node.name) != Some(i) {
+            panic!("system {} is declared twice", node.name);
+        }
+    }
+    let deps: Vec<Vec<usize>> = nodes.iter()
+        .map(|node| node.deps.iter()
+            .map(|dep| index(dep).unwrap_or_else(|| panic!("system {} depends on {}, which isn't declared", node.name, dep)))
+            .collect())
+        .collect();
+
+    let mut order = Vec::new();
+    let mut done = vec![false; nodes.len()];
+    // Everything each system runs after, directly or not.
+    let mut after: Vec<HashSet<usize>> = vec![HashSet::new(); nodes.len()];
+    while order.len() < nodes.len() {
+        match (0..nodes.len()).find(|&i| !done[i] && deps[i].iter().all(|&d| done[d])) {
+            Some(i) => {
+                for &d in deps[i].iter() {
+                    let earlier = after[d].clone();
+                    after[i].insert(d);
+                    after[i].extend(earlier);
+                }
+                done[i] = true;
+                order.push(i);
+            }
+            None => {
+                let mut stuck: Vec<usize> = (0..nodes.len()).filter(|&i| !done[i]).collect();
+                // Leave out the ones that are only waiting for the cycle.
+                while let Some(pos) = stuck.iter().position(|&i| !stuck.iter().any(|&j| deps[j].contains(&i))) {
+                    stuck.remove(pos);
+                }
+                let names: Vec<&str> = stuck.iter().map(|&i| nodes[i].name).collect();
+                panic!("systems depend on each other in a cycle: {}", names.join(", "));
+            }
+        }
+    }
+
+    for intent in intents {
+        let find = |name: &str| {
+            let i = index(name).unwrap_or_else(|| panic!("{} is handled by {}, which isn't declared", intent.name, name));
+            if !nodes[i].reads.contains(&intent.storage) && !nodes[i].writes.contains(&intent.storage) {
+                panic!("{} is handled by {}, which doesn't use it", intent.name, name);
+            }
+            i
+        };
+        let consumers: Vec<usize> = intent.consumers.iter().map(|name| find(name)).collect();
+        let cleanup: Vec<usize> = intent.cleanup.iter().map(|name| find(name)).collect();
+        for (i, node) in nodes.iter().enumerate() {
+            if !node.writes.contains(&intent.storage) || consumers.contains(&i) || cleanup.contains(&i) {
+                continue;
+            }
+            for &c in consumers.iter() {
+                if !after[c].contains(&i) {
+                    panic!("{} writes {}, but {} doesn't depend on it", node.name, intent.name, nodes[c].name);
+                }
+            }
+        }
+        for &c in cleanup.iter() {
+            for &consumer in consumers.iter() {
+                if !after[c].contains(&consumer) {
+                    panic!("{} clears {}, but doesn't depend on {}", nodes[c].name, intent.name, nodes[consumer].name);
+                }
+            }
+        }
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+    use specs_derive::*;
+    use super::{Node, Intent, order};
+
+    #[derive(Component)]
+    struct Ask {}
+
+    struct Idle {}
+
+    impl<'a> System<'a> for Idle {
+        type SystemData = ();
+        fn run(&mut self, _data: Self::SystemData) {}
+    }
+
+    // Asking and clearing up both write the intent; answering only reads it.
+    struct Writes {}
+
+    impl<'a> System<'a> for Writes {
+        type SystemData = WriteStorage<'a, Ask>;
+        fn run(&mut self, _data: Self::SystemData) {}
+    }
+
+    struct Reads {}
+
+    impl<'a> System<'a> for Reads {
+        type SystemData = ReadStorage<'a, Ask>;
+        fn run(&mut self, _data: Self::SystemData) {}
+    }
+
+    fn idle(name: &'static str, deps: &'static [&'static str]) -> Node {
+        Node::new(&Idle{}, name, deps)
+    }
+
+    fn ask() -> Intent {
+        Intent::new::<Ask>("ask", &["answer"], &["tidy"])
+    }
+
+    #[test]
+    fn keeps_declaration_order_where_it_can() {
+        let nodes = vec![idle("a", &[]), idle("b", &["d"]), idle("c", &[]), idle("d", &["a"])];
+        assert_eq!(order(&nodes, &[]), vec![0, 2, 3, 1]);
+    }
+
+    #[test]
+    fn accepts_intents_handled_in_order() {
+        let nodes = vec![
+            Node::new(&Writes{}, "tidy", &["answer"]),
+            Node::new(&Reads{}, "answer", &["ask"]),
+            Node::new(&Writes{}, "ask", &[])
+        ];
+        assert_eq!(order(&nodes, &[ask()]), vec![2, 1, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "systems depend on each other in a cycle: a, b")]
+    fn finds_cycles() {
+        let nodes = vec![idle("a", &["b"]), idle("b", &["a"]), idle("c", &["a"])];
+        order(&nodes, &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "system a depends on z, which isn't declared")]
+    fn finds_unknown_dependencies() {
+        order(&[idle("a", &["z"])], &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "system a is declared twice")]
+    fn finds_duplicate_names() {
+        order(&[idle("a", &[]), idle("b", &[]), idle("a", &[])], &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "ask writes ask, but answer doesn't depend on it")]
+    fn finds_writers_not_ordered_before_consumers() {
+        let nodes = vec![
+            Node::new(&Writes{}, "ask", &[]),
+            Node::new(&Reads{}, "answer", &[]),
+            Node::new(&Writes{}, "tidy", &["answer"])
+        ];
+        order(&nodes, &[ask()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "tidy clears ask, but doesn't depend on answer")]
+    fn finds_cleanup_before_consumers() {
+        let nodes = vec![
+            Node::new(&Writes{}, "ask", &[]),
+            Node::new(&Reads{}, "answer", &["ask"]),
+            Node::new(&Writes{}, "tidy", &["ask"])
+        ];
+        order(&nodes, &[ask()]);
+    }
+}
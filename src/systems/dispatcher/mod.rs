@@ -1,5 +1,6 @@
 use specs::prelude::World;
 use super::*;
+use crate::{WantsToMove, WantsToMelee, WantsToDance, WantsToPickupItem, WantsToDropItem, WantsToUnequipItem, WantsToUseItem, ItemUseInProgress, SufferDamage, EffectRequest, Awestruck, MakeNoise, Noise};
 
 #[cfg(target_arch="wasm32")]
 #[macro_use]
@@ -15,23 +16,27 @@ pub use single_thread::*;
 #[cfg(not(target_arch="wasm32"))]
 pub use multi_thread::*;
 
+mod profiler;
+pub use profiler::{Profiler, Timed};
+
+mod graph;
+use graph::{Node, Intent};
+
 pub trait UnifiedDispatcher {
     fn run_now(&mut self, ecs: *mut World);
 }
 
+// Each system names the systems whose results it reads, and runs after them. Nothing else
+// orders systems, so a missing dependency shows up as systems running in whatever order
+// is quickest.
 construct_dispatcher!(
     build [
         with (MonsterAISystem, "monster_ai", &[])
-        barrier
-        with (DancingMovementSystem, "dancing_movement", &[])
-        barrier
-        with (MovementSystem, "movement", &[])
-        with (MapIndexingSystem, "map_index", &[])
         with (AutoMovementSystem, "auto_movement", &[])
-        with (MeleeCombatSystem, "melee_combat", &[])
-        with (DamageSystem, "damage", &[])
-        with (AwesomenessSystem, "awesomeness", &[])
-        with (EffectsSystem, "effects", &[])
+        with (DancingMovementSystem, "dancing_movement", &["monster_ai", "auto_movement"])
+        with (MovementSystem, "movement", &["monster_ai", "auto_movement", "dancing_movement"])
+        with (MapIndexingSystem, "map_index", &["movement"])
+        with (MeleeCombatSystem, "melee_combat", &["monster_ai"])
         with (ItemPickupSystem, "item_collection", &[])
         with (ItemDropSystem, "item_drop", &[])
         with (ItemUnequipSystem, "item_unequip", &[])
@@ -44,24 +49,56 @@ construct_dispatcher!(
         with (CauseConfusionSystem, "cause_confision", &["item_use"])
         with (MakeNoiseSystem, "make_noise", &["item_use"])
         with (SpreadLiquidSystem, "spread_liquid", &["item_use"])
-        with (VisibilitySystem, "visibility", &[])
-        with (StartDancingSystem, "start_dancing", &[])
-        with (RecoverySystem, "recovery", &[])
-        barrier
-        with (NoiseSystem, "noise", &[])
-        with (ConfusionSystem, "confusion", &[])
-        barrier
-        with (DancingStatusSystem, "dancing_status", &[])
-        with (PlayerListeningSystem, "player_listening", &[])
-        with (MonsterAINoiseTrackSystem, "monster_ai_noise_track", &[])
-        barrier
-        with (ParticleSpawnSystem, "particle_spawn", &[])
-        with (LogUpdaterSystem, "log_updater", &[])
-        with (NoiseCleanupSystem, "noise_cleanup", &[])
-        with (CleanupItemUseSystem, "item_use_cleanup", &[])
+        with (DamageSystem, "damage", &["melee_combat", "do_damage"])
+        with (EffectsSystem, "effects", &["dancing_movement"])
+        with (AwesomenessSystem, "awesomeness", &["effects"])
+        with (VisibilitySystem, "visibility", &["movement"])
+        with (StartDancingSystem, "start_dancing", &["monster_ai"])
+        with (RecoverySystem, "recovery", &["monster_ai"])
+        with (NoiseSystem, "noise", &["movement", "melee_combat", "make_noise"])
+        with (ConfusionSystem, "confusion", &["monster_ai", "dancing_movement", "movement", "start_dancing", "cause_confision"])
+        with (DancingStatusSystem, "dancing_status", &["start_dancing", "dancing_movement", "effects", "awesomeness", "recovery", "do_add_poise"])
+        with (PlayerListeningSystem, "player_listening", &["noise"])
+        with (MonsterAINoiseTrackSystem, "monster_ai_noise_track", &["noise"])
+        with (ParticleSpawnSystem, "particle_spawn", &[
+            "monster_ai", "dancing_movement", "melee_combat", "item_use", "do_healing", "do_add_stamina", "do_add_poise",
+            "do_damage", "awesomeness", "confusion", "dancing_status"
+        ])
+        with (LogUpdaterSystem, "log_updater", &[
+            "monster_ai", "dancing_movement", "movement", "melee_combat", "item_collection", "item_drop", "equip",
+            "do_healing", "do_add_stamina", "do_add_poise", "do_damage", "cause_confision", "awesomeness",
+            "start_dancing", "dancing_status", "player_listening"
+        ])
+        with (StatsUpdaterSystem, "stats_updater", &["monster_ai", "item_use", "spread_liquid", "awesomeness", "start_dancing"])
+        with (NoiseCleanupSystem, "noise_cleanup", &["player_listening", "monster_ai_noise_track"])
+        with (CleanupItemUseSystem, "item_use_cleanup", &[
+            "equip", "do_healing", "do_add_stamina", "do_add_poise", "do_damage", "cause_confision", "make_noise",
+            "spread_liquid", "log_updater", "stats_updater"
+        ])
+        with (EndTurnSystem, "end_turn", &["auto_movement", "monster_ai", "dancing_movement", "recovery", "confusion"])
+    ]
+    // Components asking for something to be done, with the systems that do it and any that
+    // clear them away afterwards. Anything else writing one has to run before all of those.
+    intents [
+        intent (WantsToMove, &["movement"], &[])
+        intent (WantsToMelee, &["melee_combat"], &[])
+        intent (WantsToDance, &["start_dancing"], &[])
+        intent (WantsToPickupItem, &["item_collection"], &[])
+        intent (WantsToDropItem, &["item_drop"], &[])
+        intent (WantsToUnequipItem, &["item_unequip"], &[])
+        intent (WantsToUseItem, &["item_use"], &["item_use_cleanup"])
+        intent (ItemUseInProgress, &[
+            "equip", "do_healing", "do_add_stamina", "do_add_poise", "do_damage", "cause_confision", "make_noise", "spread_liquid",
+            "stats_updater"
+        ], &["item_use_cleanup"])
+        intent (SufferDamage, &["damage"], &[])
+        intent (EffectRequest, &["effects"], &[])
+        intent (Awestruck, &["awesomeness"], &[])
+        intent (MakeNoise, &["noise"], &[])
+        intent (Noise, &["player_listening", "monster_ai_noise_track"], &["noise_cleanup"])
     ]
 );
 
-pub fn new() -> Box<dyn UnifiedDispatcher + 'static> {
-    new_dispatch()
+pub fn new(profiler: Option<Profiler>) -> Box<dyn UnifiedDispatcher + 'static> {
+    new_dispatch(profiler)
 }
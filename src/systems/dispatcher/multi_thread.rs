@@ -14,12 +14,27 @@ impl<'a> UnifiedDispatcher for MultiThreadedDispatcher {
 }
 
 macro_rules! construct_dispatcher {
-    ( build [ $($inner:tt)* ] ) => {
-        fn new_dispatch() -> Box<dyn UnifiedDispatcher + 'static> {
+    (
+        build [ $( with ( $type:ident, $name:expr, $deps:expr ) )* ]
+        intents [ $( intent ( $intent:ident, $consumers:expr, $cleanup:expr ) )* ]
+    ) => {
+        fn new_dispatch(profiler: Option<Profiler>) -> Box<dyn UnifiedDispatcher + 'static> {
             use specs::DispatcherBuilder;
 
+            type Add = Box<dyn FnOnce(DispatcherBuilder<'static, 'static>) -> DispatcherBuilder<'static, 'static>>;
+            let mut nodes = Vec::new();
+            let mut adds: Vec<Option<Add>> = Vec::new();
+            $(
+                let system = Timed::new($type{}, $name, &profiler);
+                nodes.push(Node::new(&system, $name, $deps));
+                adds.push(Some(Box::new(move |dispatcher| dispatcher.with(system, $name, $deps))));
+            )*
+            let intents = vec![ $( Intent::new::<$intent>(stringify!($intent), $consumers, $cleanup) ),* ];
+
             let mut dispatcher = DispatcherBuilder::new();
-            expand_dispatcher!(dispatcher, $($inner)*);
+            for i in graph::order(&nodes, &intents) {
+                dispatcher = adds[i].take().unwrap()(dispatcher);
+            }
 
             let dispatch = MultiThreadedDispatcher{
                 dispatcher: dispatcher.build()
@@ -29,15 +44,3 @@ macro_rules! construct_dispatcher {
         }
     };
 }
-
-macro_rules! expand_dispatcher {
-    ($w:expr, ) => (());
-    ($dispatcher:ident , with ( $type:ident, $name:expr, $deps:expr ) $($rest:tt)*) => {
-        $dispatcher = $dispatcher.with($type{}, $name, $deps);
-        expand_dispatcher!($dispatcher, $($rest)*);
-    };
-    ($dispatcher:ident , barrier $($rest:tt)*) => {
-        $dispatcher = $dispatcher.with_barrier();
-        expand_dispatcher!($dispatcher, $($rest)*);
-    };
-}
@@ -0,0 +1,133 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use specs::prelude::*;
+use specs::RunningTime;
+
+// How many dispatches the averages are over, and so how often they're reported.
+const WINDOW: usize = 100;
+
+/// Wall time taken by each system over the last few dispatches. It's shared between the
+/// timed systems, which can be running on different threads, so it's only ever locked to
+/// record one time. Times come from `Instant`, which isn't there on wasm, so profiling is
+/// only turned on from the command line.
+#[derive(Clone)]
+pub struct Profiler {
+    profile: Arc<Mutex<Profile>>
+}
+
+struct Profile {
+    // In the order the systems first ran, which is close to the order they're dispatched.
+    systems: Vec<(&'static str, VecDeque<Duration>)>,
+    dispatches: VecDeque<Duration>,
+    since_report: usize
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            profile: Arc::new(Mutex::new(Profile {
+                systems: Vec::new(),
+                dispatches: VecDeque::new(),
+                since_report: 0
+            }))
+        }
+    }
+
+    fn record(&self, name: &'static str, time: Duration) {
+        let mut profile = self.profile.lock().unwrap();
+        let i = match profile.systems.iter().position(|(n, _)| *n == name) {
+            Some(i) => i,
+            None => {
+                profile.systems.push((name, VecDeque::new()));
+                profile.systems.len() - 1
+            }
+        };
+        push_window(&mut profile.systems[i].1, time);
+    }
+
+    /// Records how long a whole dispatch took, reporting the averages to stderr every `WINDOW` dispatches.
+    pub fn finish_dispatch(&self, time: Duration) {
+        let report_now = {
+            let mut profile = self.profile.lock().unwrap();
+            push_window(&mut profile.dispatches, time);
+            profile.since_report += 1;
+            if profile.since_report >= WINDOW {
+                profile.since_report = 0;
+                true
+            } else {
+                false
+            }
+        };
+        if report_now {
+            eprintln!("{}", self.report());
+        }
+    }
+
+    /// The average time for each system, slowest first. With the multi-threaded dispatcher
+    /// systems overlap, so they can add up to more than a whole dispatch.
+    pub fn report(&self) -> String {
+        let profile = self.profile.lock().unwrap();
+        let mut averages: Vec<(&str, Duration)> = profile.systems.iter()
+            .map(|(name, times)| (*name, average(times)))
+            .collect();
+        averages.sort_by_key(|&(_, time)| std::cmp::Reverse(time));
+
+        let mut report = format!("average over last {} dispatches: {:.3} ms", profile.dispatches.len(), millis(average(&profile.dispatches)));
+        for (name, time) in averages {
+            report.push_str(&format!("\n  {:<24} {:>8.3} ms", name, millis(time)));
+        }
+        report
+    }
+}
+
+fn push_window(times: &mut VecDeque<Duration>, time: Duration) {
+    if times.len() == WINDOW {
+        times.pop_front();
+    }
+    times.push_back(time);
+}
+
+fn average(times: &VecDeque<Duration>) -> Duration {
+    if times.is_empty() {
+        Duration::default()
+    } else {
+        times.iter().sum::<Duration>() / times.len() as u32
+    }
+}
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+/// Runs a system, timing it if there's a profiler.
+pub struct Timed<S> {
+    system: S,
+    name: &'static str,
+    profiler: Option<Profiler>
+}
+
+impl<S> Timed<S> {
+    pub fn new(system: S, name: &'static str, profiler: &Option<Profiler>) -> Self {
+        Timed { system, name, profiler: profiler.clone() }
+    }
+}
+
+impl<'a, S: System<'a>> System<'a> for Timed<S> {
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        match &self.profiler {
+            None => self.system.run(data),
+            Some(profiler) => {
+                let start = Instant::now();
+                self.system.run(data);
+                profiler.record(self.name, start.elapsed());
+            }
+        }
+    }
+
+    fn running_time(&self) -> RunningTime {
+        self.system.running_time()
+    }
+}
@@ -16,26 +16,28 @@ impl<'a> UnifiedDispatcher for SingleThreadedDispatcher<'a> {
 }
 
 macro_rules! construct_dispatcher {
-    ( build [ $($inner:tt)* ] ) => {
-        fn new_dispatch() -> Box<dyn UnifiedDispatcher + 'static> {
+    (
+        build [ $( with ( $type:ident, $name:expr, $deps:expr ) )* ]
+        intents [ $( intent ( $intent:ident, $consumers:expr, $cleanup:expr ) )* ]
+    ) => {
+        fn new_dispatch(profiler: Option<Profiler>) -> Box<dyn UnifiedDispatcher + 'static> {
+            let mut nodes = Vec::new();
+            let mut systems: Vec<Option<Box<dyn specs::RunNow<'static>>>> = Vec::new();
+            $(
+                let system = Timed::new($type{}, $name, &profiler);
+                nodes.push(Node::new(&system, $name, $deps));
+                systems.push(Some(Box::new(system)));
+            )*
+            let intents = vec![ $( Intent::new::<$intent>(stringify!($intent), $consumers, $cleanup) ),* ];
+
             let mut dispatch = SingleThreadedDispatcher{
                 systems: Vec::new()
             };
-
-            expand_dispatcher!(dispatch, $($inner)*);
+            for i in graph::order(&nodes, &intents) {
+                dispatch.systems.push(systems[i].take().unwrap());
+            }
 
             return Box::new(dispatch);
         }
     };
 }
-
-macro_rules! expand_dispatcher {
-    ($w:expr, ) => (());
-    ($dispatcher:ident , with ( $type:ident, $name:expr, $deps:expr ) $($rest:tt)*) => {
-        $dispatcher.systems.push( Box::new( $type {} ));
-        expand_dispatcher!($dispatcher, $($rest)*);
-    };
-    ($dispatcher:ident , barrier $($rest:tt)*) => {
-        expand_dispatcher!($dispatcher, $($rest)*);
-    };
-}
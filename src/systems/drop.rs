@@ -1,20 +1,19 @@
 use specs::prelude::*;
-use crate::{gamelog::GameLog, WantsToDropItem, Name, Position, InBackpack, text::capitalize};
+use crate::{events::{GameEvents, GameEvent}, WantsToDropItem, Position, InBackpack};
 
 pub struct ItemDropSystem {}
 
 impl<'a> System<'a> for ItemDropSystem {
-    type SystemData = (WriteExpect<'a, GameLog>,
+    type SystemData = (WriteExpect<'a, GameEvents>,
                        Entities<'a>,
                        WriteStorage<'a, WantsToDropItem>,
-                       ReadStorage<'a, Name>,
                        WriteStorage<'a, Position>,
                        WriteStorage<'a, InBackpack>);
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut gamelog, entities, mut wants_drop, names, mut positions, mut backpack) = data;
+        let (mut events, entities, mut wants_drop, mut positions, mut backpack) = data;
 
-        for (entity, to_drop, name) in (&entities, &wants_drop, &names).join() {
+        for (entity, to_drop) in (&entities, &wants_drop).join() {
             let mut dropper_pos : Position = Position{x: 0, y: 0};
             {
                 let dropped_pos = positions.get(entity).unwrap();
@@ -23,7 +22,7 @@ impl<'a> System<'a> for ItemDropSystem {
             }
             positions.insert(to_drop.item, Position{x: dropper_pos.x, y: dropper_pos.y}).expect("Unable to insert position");
             backpack.remove(to_drop.item);
-            gamelog.on(entity, &format!("{} {} {}.", capitalize(&name.np), name.verb("drops", "drop"), names.get(to_drop.item).unwrap().np));
+            events.single_write(GameEvent::Dropped { entity, item: to_drop.item });
         }
 
         wants_drop.clear();
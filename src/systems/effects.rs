@@ -63,7 +63,8 @@ impl<'a> System<'a> for EffectsSystem {
                         if are_enemies {
                             awestruckness.insert(target, Awestruck {
                                 poise: *poise,
-                                reason: full_reason.to_string()
+                                reason: full_reason.to_string(),
+                                by: entity
                             }).expect("Unable to insert awestruckness.");
                         }
                     }
@@ -1,33 +1,31 @@
 use specs::prelude::*;
-use crate::{gamelog::GameLog, ItemUseInProgress, Equippable, Equipped, InBackpack, Name, text::capitalize};
+use crate::{events::{GameEvents, GameEvent}, ItemUseInProgress, Equippable, Equipped, InBackpack};
 
 pub struct EquipSystem {}
 
 impl<'a> System<'a> for EquipSystem {
     type SystemData = (
-        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, GameEvents>,
         Entities<'a>,
         ReadStorage<'a, ItemUseInProgress>,
         ReadStorage<'a, Equippable>,
         WriteStorage<'a, Equipped>,
-        WriteStorage<'a, InBackpack>,
-        ReadStorage<'a, Name>
+        WriteStorage<'a, InBackpack>
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut gamelog, entities, use_in_progress, equippable, mut equipped, mut backpack, names) = data;
+        let (mut events, entities, use_in_progress, equippable, mut equipped, mut backpack) = data;
 
         for (useitem,) in (&use_in_progress,).join() {
             if let Some(can_equip) = equippable.get(useitem.item) {
                 let target_slot = can_equip.slot;
                 let target = useitem.targets[0];
-                let target_name = names.get(target).unwrap();
 
                 let mut to_unequip: Vec<Entity> = Vec::new();
-                for (item_entity, already_equipped, name) in (&entities, &equipped, &names).join() {
+                for (item_entity, already_equipped) in (&entities, &equipped).join() {
                     if already_equipped.owner == target && already_equipped.slot == target_slot {
                         to_unequip.push(item_entity);
-                        gamelog.on(target, &format!("{} {} {}.", capitalize(&target_name.np), target_name.verb("unequips", "unequip"), name.np));
+                        events.single_write(GameEvent::Unequipped { entity: target, item: item_entity });
                     }
                 }
                 for item in to_unequip.iter() {
@@ -37,7 +35,7 @@ impl<'a> System<'a> for EquipSystem {
 
                 equipped.insert(useitem.item, Equipped{ owner: target, slot: target_slot }).expect("Unable to insert equipped component");
                 backpack.remove(useitem.item);
-                gamelog.on(target, &format!("{} {} {}.", capitalize(&target_name.np), target_name.verb("equips", "equip"), names.get(useitem.item).unwrap().np));
+                events.single_write(GameEvent::Equipped { entity: target, item: useitem.item });
             }
         }
     }
@@ -0,0 +1,79 @@
+use specs::prelude::*;
+use crate::{Energy, MyTurn, Equipped, SpeedPenalty, Turn, Resting, systems::recovery};
+
+/// Energy needed to act, and what most actions cost.
+pub const TURN_ENERGY: i32 = 100;
+pub const ACTION_COST: i32 = TURN_ENERGY;
+pub const DANCE_STEP_COST: i32 = 75;
+pub const REST_COST: i32 = 50;
+
+/// How fast things act unless their data says otherwise.
+pub const NORMAL_SPEED: i32 = 100;
+// However much armour slows something down, it still gets to act.
+const MIN_SPEED: i32 = 10;
+
+pub struct EndTurnSystem {}
+
+impl<'a> System<'a> for EndTurnSystem {
+    type SystemData = (
+        WriteStorage<'a, MyTurn>,
+        WriteStorage<'a, Energy>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut my_turns, mut energies) = data;
+
+        for (my_turn, energy) in (&my_turns, &mut energies).join() {
+            energy.energy -= my_turn.cost;
+        }
+
+        my_turns.clear();
+    }
+}
+
+/// Lets time pass until something can act and gives it the turn, returning whether it's the
+/// player's. The player goes first, then everything else that's ready acts together. Everything
+/// recovers as each turn passes, and stops resting when it gets its next turn.
+pub fn give_turns(ecs: &mut World) -> bool {
+    let player = *ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let mut energies = ecs.write_storage::<Energy>();
+    let mut my_turns = ecs.write_storage::<MyTurn>();
+    let equipped = ecs.read_storage::<Equipped>();
+    let penalties = ecs.read_storage::<SpeedPenalty>();
+    let mut turn = ecs.write_resource::<Turn>();
+
+    if (&energies).join().next().is_none() {
+        return false;
+    }
+
+    loop {
+        if matches!(energies.get(player), Some(e) if e.energy >= TURN_ENERGY) {
+            my_turns.insert(player, MyTurn { cost: ACTION_COST }).expect("Unable to insert turn");
+            ecs.write_storage::<Resting>().remove(player);
+            return true;
+        }
+
+        let ready: Vec<Entity> = (&entities, &energies).join()
+            .filter(|(_, energy)| energy.energy >= TURN_ENERGY)
+            .map(|(entity, _)| entity)
+            .collect();
+        if !ready.is_empty() {
+            for entity in ready {
+                my_turns.insert(entity, MyTurn { cost: ACTION_COST }).expect("Unable to insert turn");
+                ecs.write_storage::<Resting>().remove(entity);
+            }
+            return false;
+        }
+
+        *turn += 1;
+        recovery::recover(ecs, *turn);
+        for (entity, energy) in (&entities, &mut energies).join() {
+            let penalty: i32 = (&equipped, &penalties).join()
+                .filter(|(eq, _)| eq.owner == entity)
+                .map(|(_, p)| p.penalty)
+                .sum();
+            energy.energy += i32::max(MIN_SPEED, energy.speed - penalty);
+        }
+    }
+}
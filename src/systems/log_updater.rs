@@ -1,8 +1,8 @@
 use specs::prelude::*;
 use rltk::{Point};
-use crate::{gamelog::{PlayerLog, GameLog, Scope}, Player, Viewshed, Position, InBackpack};
+use crate::{gamelog::{PlayerLog, GameLog, Item, Scope, Category}, events::{GameEvent, GameEvents, Hindrance, FleeReason}, text::capitalize, Turn, Player, Viewshed, Position, InBackpack, Name, Health, Stamina, Poise};
 
-const MAX_LOG_SIZE: u32 = 1000;
+const MAX_LOG_SIZE: usize = 1000;
 
 pub struct LogUpdaterSystem {}
 
@@ -10,22 +10,35 @@ impl<'a> System<'a> for LogUpdaterSystem {
     type SystemData = (
         WriteExpect<'a, GameLog>,
         WriteExpect<'a, PlayerLog>,
+        ReadExpect<'a, GameEvents>,
+        ReadExpect<'a, Turn>,
         ReadStorage<'a, Player>,
         ReadStorage<'a, Viewshed>,
         ReadStorage<'a, Position>,
-        ReadStorage<'a, InBackpack>
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Name>
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             mut game_log,
             mut player_log,
+            events,
+            turn,
             players,
             viewsheds,
             positions,
-            in_backpacks
+            in_backpacks,
+            names
         ) = data;
 
+        let game_log = &mut *game_log;
+        for event in events.read(&mut game_log.events) {
+            if let Some(item) = describe(event, &names) {
+                game_log.entries.push(item);
+            }
+        }
+
         for (_player, viewshed) in (&players, &viewsheds).join() {
             for item in game_log.entries.iter() {
                 let want =
@@ -49,15 +62,132 @@ impl<'a> System<'a> for LogUpdaterSystem {
                         }
                     };
                 if want {
-                    player_log.entries.push(item.message.to_string());
+                    player_log.insert(*turn, item.category, &item.message);
                 }
             }
             game_log.entries.clear();
         }
 
-        let log_size = player_log.entries.len() as u32;
-        if log_size > MAX_LOG_SIZE {
-            player_log.entries.drain(0..((log_size - MAX_LOG_SIZE) as usize));
+        player_log.trim(MAX_LOG_SIZE);
+    }
+}
+
+/// Puts an event into words, along with who has to be seen for the player to hear about it.
+/// Events about things that have since lost their names go unmentioned.
+fn describe(event: &GameEvent, names: &ReadStorage<Name>) -> Option<Item> {
+    let (on, message) = match event {
+        GameEvent::Attacked { attacker, target, damage } => {
+            let name = names.get(*attacker)?;
+            let target_name = names.get(*target)?;
+            if *damage == 0 {
+                (*target, format!("{} {} unable to hurt {}", capitalize(&name.np), name.verb("is", "are"), target_name.np))
+            } else {
+                (*target, format!("{} {} {} ({} {})", capitalize(&name.np), name.verb("hits", "hit"), target_name.np, damage, Health::NAME))
+            }
+        }
+        GameEvent::Damaged { user, item, target, damage } => {
+            let name = names.get(*user)?;
+            (*user, format!("{} {} {} on {}, inflicting {} hp.", capitalize(&name.np), name.verb("uses", "use"), names.get(*item)?.np, names.get(*target)?.np, damage))
+        }
+        GameEvent::Confused { user, item, target } => {
+            let name = names.get(*user)?;
+            let target_name = names.get(*target)?;
+            (*user, format!("{} {} {} on {}, confusing {}.", capitalize(&name.np), name.verb("uses", "use"), names.get(*item)?.np, target_name.np, target_name.pronoun))
+        }
+        GameEvent::Healed { entity, item, amount } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} {}, healing {} {}.", capitalize(&name.np), name.verb("drinks", "drink"), names.get(*item)?.np, amount, Health::NAME))
+        }
+        GameEvent::GainedStamina { entity, item, amount } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} {}, gaining {} {}.", capitalize(&name.np), name.verb("drinks", "drink"), names.get(*item)?.np, amount, Stamina::NAME))
+        }
+        GameEvent::GainedPoise { entity, item, amount } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} {}, gaining {} {}.", capitalize(&name.np), name.verb("drinks", "drink"), names.get(*item)?.np, amount, Poise::NAME))
+        }
+        GameEvent::Died { name, at, .. } => {
+            return Some(Item {
+                scope: Scope::AT { at: *at },
+                category: category(event),
+                message: format!("{} {} dead", capitalize(&name.np), name.verb("is", "are"))
+            });
+        }
+        GameEvent::Awed { entity, reason, poise, .. } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} awed by {} ({} {}).", capitalize(&name.np), name.verb("is", "are"), reason, poise, Poise::NAME))
+        }
+        GameEvent::CouldntDance { entity, hindrance } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} too {} to dance.", capitalize(&name.np), name.verb("is", "are"), hindrance_adjective(*hindrance)))
         }
+        GameEvent::StartedDance { entity, dance } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} the {} dance.", capitalize(&name.np), name.verb("starts", "start"), dance.name))
+        }
+        GameEvent::StoppedDancing { entity, hindrance, poise } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} too {} to continue dancing ({} {}).", capitalize(&name.np), name.verb("is", "are"), hindrance_adjective(*hindrance), poise, Poise::NAME))
+        }
+        GameEvent::FailedDance { entity, poise } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} {} dance ({} {}).", capitalize(&name.np), name.verb("fails", "fail"), name.pronoun_pos, poise, Poise::NAME))
+        }
+        GameEvent::FinishedDance { entity } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} dancing.", capitalize(&name.np), name.verb("finishes", "finish")))
+        }
+        GameEvent::Fled { entity, reason, .. } => {
+            let name = names.get(*entity)?;
+            let how = match reason {
+                FleeReason::Hurt => format!("for {} life", name.pronoun_pos),
+                FleeReason::Tired => "to rest".to_string(),
+                FleeReason::Shamed => "in shame".to_string()
+            };
+            (*entity, format!("{} {} {}.", capitalize(&name.np), name.verb("flees", "flee"), how))
+        }
+        GameEvent::Slipped { entity, liquid } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} on the {}.", capitalize(&name.np), name.verb("slips", "slip"), liquid.name()))
+        }
+        // The stains are there to see for themselves.
+        GameEvent::Stained { .. } => return None,
+        GameEvent::PickedUp { entity, item } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} up {}.", capitalize(&name.np), name.verb("picks", "pick"), names.get(*item)?.np))
+        }
+        GameEvent::Dropped { entity, item } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} {}.", capitalize(&name.np), name.verb("drops", "drop"), names.get(*item)?.np))
+        }
+        GameEvent::Equipped { entity, item } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} {}.", capitalize(&name.np), name.verb("equips", "equip"), names.get(*item)?.np))
+        }
+        GameEvent::Unequipped { entity, item } => {
+            let name = names.get(*entity)?;
+            (*entity, format!("{} {} {}.", capitalize(&name.np), name.verb("unequips", "unequip"), names.get(*item)?.np))
+        }
+    };
+    Some(Item { scope: Scope::ON { on }, category: category(event), message })
+}
+
+fn category(event: &GameEvent) -> Category {
+    match event {
+        GameEvent::Attacked { .. } | GameEvent::Damaged { .. } | GameEvent::Died { .. } |
+        GameEvent::Fled { .. } | GameEvent::Slipped { .. } => Category::Combat,
+        GameEvent::Awed { .. } | GameEvent::CouldntDance { .. } | GameEvent::StartedDance { .. } |
+        GameEvent::StoppedDancing { .. } | GameEvent::FailedDance { .. } | GameEvent::FinishedDance { .. } => Category::Dance,
+        GameEvent::Confused { .. } | GameEvent::Healed { .. } | GameEvent::GainedStamina { .. } | GameEvent::GainedPoise { .. } |
+        GameEvent::PickedUp { .. } | GameEvent::Dropped { .. } | GameEvent::Equipped { .. } | GameEvent::Unequipped { .. } |
+        GameEvent::Stained { .. } => Category::Item
+    }
+}
+
+fn hindrance_adjective(hindrance: Hindrance) -> &'static str {
+    match hindrance {
+        Hindrance::Confused => "confused",
+        Hindrance::Tired => "tired",
+        Hindrance::Intimidated => "intimidated"
     }
 }
@@ -1,14 +1,13 @@
 use specs::prelude::*;
 use rltk::{Point};
-use crate::{CombatStats, Health, WantsToMelee, Name, SufferDamage, gamelog::GameLog, text::capitalize, MeleePowerBonus, DefenceBonus, Equipped, Position, systems::particle_system::ParticleBuilder, HasAggroedMosters, Stamina, MakeNoise};
+use crate::{CombatStats, Health, WantsToMelee, SufferDamage, events::{GameEvents, GameEvent}, MeleePowerBonus, DefenceBonus, Equipped, Position, systems::particle_system::ParticleBuilder, HasAggroedMosters, Stamina, MakeNoise, HurtBy};
 
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
     type SystemData = (Entities<'a>,
-                       WriteExpect<'a, GameLog>,
+                       WriteExpect<'a, GameEvents>,
                        WriteStorage<'a, WantsToMelee>,
-                       ReadStorage<'a, Name>,
                        ReadStorage<'a, CombatStats>,
                        ReadStorage<'a, Health>,
                        WriteStorage<'a, Stamina>,
@@ -19,12 +18,13 @@ impl<'a> System<'a> for MeleeCombatSystem {
                        ReadStorage<'a, Position>,
                        WriteExpect<'a, ParticleBuilder>,
                        WriteStorage<'a, HasAggroedMosters>,
-                       WriteStorage<'a, MakeNoise>);
+                       WriteStorage<'a, MakeNoise>,
+                       WriteStorage<'a, HurtBy>);
 
     fn run(&mut self, data : Self::SystemData) {
-        let (entities, mut gamelog, mut wants_melee, names, combat_stats, health, mut stamina, mut inflict_damage, melee_power_bonuses, defence_bonuses, equipped, positions, mut particle_builder, mut has_agroed, mut make_noises) = data;
+        let (entities, mut events, mut wants_melee, combat_stats, health, mut stamina, mut inflict_damage, melee_power_bonuses, defence_bonuses, equipped, positions, mut particle_builder, mut has_agroed, mut make_noises, mut hurt_by) = data;
 
-        for (entity, wants_melee, health, mut stamina, name, stats) in (&entities, &wants_melee, &health, &mut stamina, &names, &combat_stats).join() {
+        for (entity, wants_melee, health, mut stamina, stats) in (&entities, &wants_melee, &health, &mut stamina, &combat_stats).join() {
             has_agroed.insert(entity, HasAggroedMosters {}).expect("Failed to insert agro.");
 
             if health.health > 0 && stamina.stamina > 0 {
@@ -39,7 +39,6 @@ impl<'a> System<'a> for MeleeCombatSystem {
 
                 let target_stats = combat_stats.get(wants_melee.target).unwrap();
                 if health.health > 0 {
-                    let target_name = names.get(wants_melee.target).unwrap();
 
                     let mut defensive_bonus = 0;
                     for (_item_entity, defence_bonus, equipped_by) in (&entities, &defence_bonuses, &equipped).join() {
@@ -61,11 +60,10 @@ impl<'a> System<'a> for MeleeCombatSystem {
                     }
 
                     let damage = i32::max(0, (stats.power + offensive_bonus) - (target_stats.defence + defensive_bonus));
-                    if damage == 0 {
-                        gamelog.on(wants_melee.target, &format!("{} {} unable to hurt {}", capitalize(&name.np), name.verb("is", "are"), target_name.np));
-                    } else {
-                        gamelog.on(wants_melee.target, &format!("{} {} {} ({} {})", capitalize(&name.np), name.verb("hits", "hit"), target_name.np, damage, Health::NAME));
+                    events.single_write(GameEvent::Attacked { attacker: entity, target: wants_melee.target, damage });
+                    if damage > 0 {
                         SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                        hurt_by.insert(wants_melee.target, HurtBy { by: entity }).expect("Unable to insert hurt by");
                     }
                 }
             }
@@ -74,3 +72,89 @@ impl<'a> System<'a> for MeleeCombatSystem {
         wants_melee.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+    use crate::testing::Fixture;
+    use crate::events::GameEvent;
+    use crate::{WantsToMelee, SufferDamage, Stamina, MakeNoise, CombatStats, Health, Name, MeleePowerBonus, Equipped, EquipmentSlot};
+    use super::MeleeCombatSystem;
+
+    #[test]
+    fn hits_for_power_less_defence() {
+        let mut fixture = Fixture::new("
+            ####
+            #@.#
+            ####");
+        let player = fixture.player();
+        let rabbit = fixture.spawn("rabbit", 2, 1);
+        fixture.insert(player, WantsToMelee{ target: rabbit });
+
+        fixture.run(MeleeCombatSystem{});
+
+        assert_eq!(fixture.get::<SufferDamage>(rabbit).unwrap().amount, vec![4]);
+        assert_eq!(fixture.get::<Stamina>(player).unwrap().stamina, 9);
+        assert!(fixture.get::<MakeNoise>(player).is_some());
+        assert!(fixture.get::<WantsToMelee>(player).is_none());
+        assert_eq!(fixture.log(), vec!["You hit the rabbit (4 health)"]);
+    }
+
+    #[test]
+    fn cant_hurt_through_defence() {
+        let mut fixture = Fixture::new("
+            ####
+            #@.#
+            ####");
+        let player = fixture.player();
+        let rabbit = fixture.spawn("rabbit", 2, 1);
+        fixture.insert(rabbit, WantsToMelee{ target: player });
+
+        fixture.run(MeleeCombatSystem{});
+
+        assert!(fixture.get::<SufferDamage>(player).is_none());
+        assert_eq!(fixture.log(), vec!["The rabbit is unable to hurt you"]);
+    }
+
+    #[test]
+    fn weapon_adds_power() {
+        let mut fixture = Fixture::new("
+            ####
+            #@.#
+            ####");
+        let player = fixture.player();
+        let thing = fixture.place(2, 1)
+            .with(Name::new_regular("thing"))
+            .with(CombatStats{ defence: 0, power: 3 })
+            .with(Health{ max_health: 1, health: 1 })
+            .with(Stamina{ stamina: 1, max_stamina: 1 })
+            .build();
+        fixture.place(2, 1)
+            .with(MeleePowerBonus{ power: 2 })
+            .with(Equipped{ owner: thing, slot: EquipmentSlot::Melee })
+            .build();
+        fixture.insert(thing, WantsToMelee{ target: player });
+
+        fixture.run(MeleeCombatSystem{});
+
+        assert_eq!(fixture.get::<SufferDamage>(player).unwrap().amount, vec![3]);
+        assert_eq!(fixture.log(), vec!["The thing hits you (3 health)"]);
+    }
+
+    #[test]
+    fn too_tired_to_attack() {
+        let mut fixture = Fixture::new("
+            ####
+            #@.#
+            ####");
+        let player = fixture.player();
+        let rabbit = fixture.spawn("rabbit", 2, 1);
+        fixture.ecs.write_storage::<Stamina>().get_mut(player).unwrap().stamina = 0;
+        fixture.insert(player, WantsToMelee{ target: rabbit });
+
+        fixture.run(MeleeCombatSystem{});
+
+        assert!(fixture.get::<SufferDamage>(rabbit).is_none());
+        assert!(fixture.events().iter().all(|event| !matches!(event, GameEvent::Attacked { .. })));
+    }
+}
@@ -1,5 +1,5 @@
 mod dispatcher;
-pub use dispatcher::UnifiedDispatcher;
+pub use dispatcher::{UnifiedDispatcher, Profiler};
 
 mod visibility_system;
 pub use visibility_system::VisibilitySystem;
@@ -49,6 +49,8 @@ mod movement;
 pub use movement::MovementSystem;
 mod log_updater;
 pub use log_updater::LogUpdaterSystem;
+mod stats_updater;
+pub use stats_updater::StatsUpdaterSystem;
 pub mod dancing;
 pub use dancing::{StartDancingSystem, DancingMovementSystem, DancingStatusSystem};
 mod recovery;
@@ -57,7 +59,9 @@ pub mod noise;
 pub use noise::{NoiseSystem, NoiseCleanupSystem, PlayerListeningSystem};
 mod confusion;
 pub use confusion::{ConfusionSystem};
+pub mod initiative;
+pub use initiative::EndTurnSystem;
 
-pub fn build() -> Box<dyn UnifiedDispatcher + 'static> {
-    dispatcher::new()
+pub fn build(profiler: Option<Profiler>) -> Box<dyn UnifiedDispatcher + 'static> {
+    dispatcher::new(profiler)
 }
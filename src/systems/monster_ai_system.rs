@@ -1,7 +1,8 @@
 use specs::prelude::*;
 use serde::{Serialize, Deserialize};
-use rltk::{Point, RandomNumberGenerator};
-use crate::{Map, MapPather, Viewshed, Position, Monster, MonsterAI, WantsToMelee, Confusion, systems::particle_system::ParticleBuilder, RunState, Dancing, CanDoDances, HasAggroedMosters, WantsToMove, WantsToDance, Health, Stamina, Poise, dancing, gamelog::GameLog, text::{capitalize}, Name, Resting, Noise, systems::noise::can_hear, MonsterAINoiseRecord, Turn, InFaction};
+use rltk::Point;
+use crate::random::RandomNumberGenerator;
+use crate::{Map, MapPather, Viewshed, Position, Monster, MonsterAI, WantsToMelee, Confusion, systems::particle_system::ParticleBuilder, MyTurn, Dancing, CanDoDances, HasAggroedMosters, WantsToMove, WantsToDance, Health, Stamina, Poise, dancing, events::{GameEvents, GameEvent, FleeReason}, Resting, Noise, systems::noise::can_hear, MonsterAINoiseRecord, Turn, InFaction, HurtBy, AwedBy};
 
 #[derive(PartialEq, Clone, Serialize, Deserialize)]
 pub struct PathInfo {
@@ -33,8 +34,8 @@ impl<'a> System<'a> for MonsterAISystem {
     type SystemData = (WriteExpect<'a, Map>,
                        ReadExpect<'a, Point>,
                        ReadExpect<'a, Entity>,
-                       ReadExpect<'a, RunState>,
-                       WriteExpect<'a, GameLog>,
+                       ReadStorage<'a, MyTurn>,
+                       WriteExpect<'a, GameEvents>,
                        Entities<'a>,
                        WriteStorage<'a, Viewshed>,
                        ReadStorage<'a, Position>,
@@ -52,15 +53,14 @@ impl<'a> System<'a> for MonsterAISystem {
                        ReadStorage<'a, Health>,
                        ReadStorage<'a, Stamina>,
                        ReadStorage<'a, Poise>,
-                       ReadStorage<'a, Name>,
-                       WriteStorage<'a, Resting>);
+                       WriteStorage<'a, Resting>,
+                       ReadStorage<'a, HurtBy>,
+                       ReadStorage<'a, AwedBy>);
 
     fn run(&mut self, data: Self::SystemData) {
-        let (map, player_pos, player_entity, runstate, mut gamelog, entities, viewsheds, pos, mut confused, monster, mut monster_ai, mut wants_to_melee, mut particle_builder, dancers, mut rng, can_do_dances, has_agroed, mut wants_to_moves, mut want_to_dancers, health, stamina, poise, names, mut resting) = data;
+        let (map, player_pos, player_entity, my_turns, mut events, entities, viewsheds, pos, mut confused, monster, mut monster_ai, mut wants_to_melee, mut particle_builder, dancers, mut rng, can_do_dances, has_agroed, mut wants_to_moves, mut want_to_dancers, health, stamina, poise, mut resting, hurt_by, awed_by) = data;
 
-        if *runstate != RunState::MonsterTurn { return; }
-
-        for (entity, viewshed, pos, _monster, ai, health, stamina, poise, name) in (&entities, &viewsheds, &pos, &monster, &mut monster_ai, &health, &stamina, &poise, &names).join() {
+        for (entity, viewshed, pos, _monster, ai, health, stamina, poise, _my_turn) in (&entities, &viewsheds, &pos, &monster, &mut monster_ai, &health, &stamina, &poise, &my_turns).join() {
             if let Some(_) = dancers.get(entity) {
                 continue;
             }
@@ -114,7 +114,7 @@ impl<'a> System<'a> for MonsterAISystem {
                 } else if health.health < health.max_health / 10 {
                     chose_action = true;
                     particle_builder.request(pos.x, pos.y, Health::colour(), rltk::to_cp437('‼'), 200.0);
-                    gamelog.on(entity, &format!("{} {} for {} life.", capitalize(&name.np), name.verb("flees", "flee"), name.pronoun_pos));
+                    events.single_write(GameEvent::Fled { entity, reason: FleeReason::Hurt, by: hurt_by.get(entity).map(|h| h.by) });
                     new_state = MonsterAIState::MOVING {
                         goal: MovementGoal::Flee,
                         path: None
@@ -122,7 +122,7 @@ impl<'a> System<'a> for MonsterAISystem {
                 } else if stamina.stamina == 0 {
                     chose_action = true;
                     particle_builder.request(pos.x, pos.y, Stamina::colour(), rltk::to_cp437('‼'), 200.0);
-                    gamelog.on(entity, &format!("{} {} to rest.", capitalize(&name.np), name.verb("flees", "flee")));
+                    events.single_write(GameEvent::Fled { entity, reason: FleeReason::Tired, by: None });
                     new_state = MonsterAIState::MOVING {
                         goal: MovementGoal::Flee,
                         path: None
@@ -130,7 +130,7 @@ impl<'a> System<'a> for MonsterAISystem {
                 } else if poise.poise == 0 {
                     chose_action = true;
                     particle_builder.request(pos.x, pos.y, Poise::colour(), rltk::to_cp437('‼'), 200.0);
-                    gamelog.on(entity, &format!("{} {} in shame.", capitalize(&name.np), name.verb("flees", "flee")));
+                    events.single_write(GameEvent::Fled { entity, reason: FleeReason::Shamed, by: awed_by.get(entity).map(|a| a.by) });
                     new_state = MonsterAIState::MOVING {
                         goal: MovementGoal::Flee,
                         path: None
@@ -381,7 +381,7 @@ fn look_for_dance_spot<'a>(current_pos: &Point, range: &Vec<Point>, dance: &danc
     None
 }
 
-fn is_good_start_position<'a>(start: &Point, dance: &dancing::Dance, map: &Map, dancers: &ReadStorage<'a, Dancing>) -> bool {
+pub fn is_good_start_position<'a>(start: &Point, dance: &dancing::Dance, map: &Map, dancers: &ReadStorage<'a, Dancing>) -> bool {
     let start_idx = map.point_idx(start);
     let mut at = *start;
     for dancer in dancers.join() {
@@ -389,7 +389,7 @@ fn is_good_start_position<'a>(start: &Point, dance: &dancing::Dance, map: &Map,
             return false;
         }
     }
-    for step in dance.steps().iter() {
+    for step in dance.steps.iter() {
         at = at + step.direction;
         let at_idx = map.point_idx(&at);
         if at_idx != start_idx && map.blocked[at_idx] {
@@ -1,6 +1,7 @@
 use specs::prelude::*;
-use rltk::{Point, RandomNumberGenerator};
-use crate::{WantsToMove, Position, Viewshed, Map, gamelog::GameLog, text::capitalize, Name, MakeNoise, factions::Faction, Confusion};
+use rltk::Point;
+use crate::random::RandomNumberGenerator;
+use crate::{WantsToMove, Position, Viewshed, Map, events::{GameEvents, GameEvent}, MakeNoise, factions::Faction, Confusion, liquids::Liquid};
 
 pub struct MovementSystem {}
 
@@ -10,12 +11,11 @@ impl<'a> System<'a> for MovementSystem {
         ReadExpect<'a, Entity>,
         WriteExpect<'a, Point>,
         ReadExpect<'a, Map>,
-        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, GameEvents>,
         WriteExpect<'a, RandomNumberGenerator>,
         WriteStorage<'a, WantsToMove>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, Viewshed>,
-        ReadStorage<'a, Name>,
         WriteStorage<'a, MakeNoise>,
         ReadStorage<'a, Confusion>
     );
@@ -26,34 +26,23 @@ impl<'a> System<'a> for MovementSystem {
             player,
             mut player_pos,
             map,
-            mut gamelog,
+            mut events,
             mut rng,
             mut wants_to_moves,
             mut positions,
             mut viewsheds,
-            names,
             mut make_noises,
             confusion
         ) = data;
 
-        for (entity, wants_move, mut pos, name) in (&entities, &wants_to_moves, &mut positions, &names).join() {
+        for (entity, wants_move, mut pos) in (&entities, &wants_to_moves, &mut positions).join() {
             let source_idx = map.point_idx(&wants_move.source);
-            let stains = &map.stains[source_idx];
+            let mut stains: Vec<&Liquid> = map.stains[source_idx].iter().collect();
+            stains.sort_by_key(|l| l.name()); // so seeded runs don't depend on hash order
             let (dest, did_slip) =
                 if stains.len() > 0 && rng.roll_dice(1, 10) < 5 {
-                    let slip_on_i = rng.range(0, stains.len());
-                    let mut slip_on = None;
-                    for (i, stain) in stains.iter().enumerate() {
-                        if i == slip_on_i {
-                            slip_on = Some(stain);
-                            break;
-                        }
-                    }
-                    if let Some(slip_on) = slip_on {
-                        gamelog.on(entity, &format!("{} {} on the {}.", capitalize(&name.np), name.verb("slips", "slip"), slip_on.name()));
-                    } else {
-                        gamelog.on(entity, &format!("{} {}.", capitalize(&name.np), name.verb("slips", "slip")));
-                    }
+                    let slip_on = stains[rng.range(0, stains.len())];
+                    events.single_write(GameEvent::Slipped { entity, liquid: *slip_on });
                     let rand_dest = Point::new(
                         pos.x + rng.roll_dice(1, 3) - 2,
                         pos.y + rng.roll_dice(1, 3) - 2
@@ -104,3 +93,47 @@ impl<'a> System<'a> for MovementSystem {
         wants_to_moves.clear();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+    use rltk::Point;
+    use crate::testing::Fixture;
+    use crate::{WantsToMove, Position, Viewshed, MakeNoise};
+    use super::MovementSystem;
+
+    #[test]
+    fn player_moves_and_makes_noise() {
+        let mut fixture = Fixture::new("
+            #####
+            #@..#
+            #####");
+        let player = fixture.player();
+        fixture.ecs.write_storage::<Viewshed>().get_mut(player).unwrap().dirty = false;
+        fixture.insert(player, WantsToMove{ source: Point::new(1, 1), destination: Point::new(2, 1) });
+
+        fixture.run(MovementSystem{});
+
+        let pos = fixture.get::<Position>(player).unwrap();
+        assert_eq!((pos.x, pos.y), (2, 1));
+        assert_eq!(*fixture.ecs.fetch::<Point>(), Point::new(2, 1));
+        assert!(fixture.get::<Viewshed>(player).unwrap().dirty);
+        assert_eq!(fixture.get::<MakeNoise>(player).unwrap().description, "movement");
+    }
+
+    #[test]
+    fn monsters_move_quietly() {
+        let mut fixture = Fixture::new("
+            #####
+            #@..#
+            #####");
+        let rabbit = fixture.spawn("rabbit", 3, 1);
+        fixture.insert(rabbit, WantsToMove{ source: Point::new(3, 1), destination: Point::new(2, 1) });
+
+        fixture.run(MovementSystem{});
+
+        let pos = fixture.get::<Position>(rabbit).unwrap();
+        assert_eq!((pos.x, pos.y), (2, 1));
+        assert!(fixture.get::<MakeNoise>(rabbit).is_none());
+    }
+}
@@ -1,6 +1,6 @@
 use specs::prelude::*;
 use rltk::{Point};
-use crate::{MakeNoise, Noise, gamelog::PlayerLog};
+use crate::{MakeNoise, Noise, gamelog::{PlayerLog, Category}, Turn};
 
 pub struct NoiseSystem {}
 
@@ -38,18 +38,20 @@ pub struct PlayerListeningSystem {}
 impl<'a> System<'a> for PlayerListeningSystem {
     type SystemData = (
         WriteExpect<'a, PlayerLog>,
+        ReadExpect<'a, Turn>,
         WriteStorage<'a, Noise>
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             mut player_log,
+            turn,
             noises
         ) = data;
 
         for (noise,) in (&noises,).join() {
             if noise.surprising && can_hear(&noise.location, noise) {
-                player_log.insert(&format!("You hear {}.", noise.description));
+                player_log.insert(*turn, Category::Noise, &format!("You hear {}.", noise.description));
             }
         }
     }
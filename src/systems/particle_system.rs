@@ -1,6 +1,5 @@
 use specs::prelude::*;
 use rltk::{RGB};
-use crate::{Rltk, ParticleLifetime, Position, Renderable};
 
 pub struct ParticleSpawnSystem {}
 
@@ -12,54 +11,69 @@ struct ParticleRequest {
     lifetime: f32
 }
 
+// Particles live outside the ECS: they only matter for drawing, and their lifetimes
+// depend on frame times, which mustn't leak into the simulation.
+pub struct Particle {
+    pub x: i32,
+    pub y: i32,
+    pub fg: RGB,
+    pub glyph: rltk::FontCharType,
+    lifetime_ms: f32
+}
+
 pub struct ParticleBuilder {
-    requests: Vec<ParticleRequest>
+    requests: Vec<ParticleRequest>,
+    pub particles: Vec<Particle>,
+    // How long particles last, as a percentage of what they ask for.
+    lifetime_percent: u32
 }
 
-pub fn cull_dead_particles(ecs: &mut World, ctx: &Rltk) {
-    let mut dead_particles: Vec<Entity> = Vec::new();
-    {
-        let mut particles = ecs.write_storage::<ParticleLifetime>();
-        let entities = ecs.entities();
-        for (entity, mut particle) in (&entities, &mut particles).join() {
-            particle.lifetime_ms -= ctx.frame_time_ms;
-            if particle.lifetime_ms < 0.0 {
-                dead_particles.push(entity);
-            }
-        }
-    }
-    for dead in dead_particles.iter() {
-        ecs.delete_entity(*dead).expect("Particle will not die");
+pub fn cull_dead_particles(ecs: &mut World, frame_time_ms: f32) {
+    let mut particle_builder = ecs.fetch_mut::<ParticleBuilder>();
+    for particle in particle_builder.particles.iter_mut() {
+        particle.lifetime_ms -= frame_time_ms;
     }
+    particle_builder.particles.retain(|p| p.lifetime_ms >= 0.0);
 }
 
 impl ParticleBuilder {
     pub fn new() -> ParticleBuilder {
-        ParticleBuilder{ requests: Vec::new() }
+        ParticleBuilder{ requests: Vec::new(), particles: Vec::new(), lifetime_percent: 100 }
+    }
+
+    pub fn set_lifetime_percent(&mut self, percent: u32) {
+        self.lifetime_percent = percent;
     }
 
     pub fn request(&mut self, x: i32, y: i32, fg: RGB, glyph: rltk::FontCharType, lifetime: f32) {
+        if self.lifetime_percent == 0 {
+            return;
+        }
+        let lifetime = lifetime * self.lifetime_percent as f32 / 100.0;
         self.requests.push(
             ParticleRequest{ x, y, fg, glyph, lifetime }
         )
     }
+
+    pub fn clear(&mut self) {
+        self.requests.clear();
+        self.particles.clear();
+    }
 }
 
 impl<'a> System<'a> for ParticleSpawnSystem {
-    type SystemData = (Entities<'a>,
-                       WriteStorage<'a, Position>,
-                       WriteStorage<'a, Renderable>,
-                       WriteStorage<'a, ParticleLifetime>,
-                       WriteExpect<'a, ParticleBuilder>);
+    type SystemData = WriteExpect<'a, ParticleBuilder>;
 
-    fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut positions, mut renderables, mut particles, mut particle_builder) = data;
-        for new_particle in particle_builder.requests.iter() {
-            let p = entities.create();
-            positions.insert(p, Position{ x: new_particle.x, y: new_particle.y }).expect("Unable to insert position");
-            renderables.insert(p, Renderable{ fg: new_particle.fg, glyph: new_particle.glyph, render_order: 0 }).expect("Unable to insert renderable");
-            particles.insert(p, ParticleLifetime{ lifetime_ms: new_particle.lifetime }).expect("Unable to insert lifetime");
+    fn run(&mut self, mut particle_builder: Self::SystemData) {
+        let requests: Vec<ParticleRequest> = particle_builder.requests.drain(..).collect();
+        for new_particle in requests {
+            particle_builder.particles.push(Particle {
+                x: new_particle.x,
+                y: new_particle.y,
+                fg: new_particle.fg,
+                glyph: new_particle.glyph,
+                lifetime_ms: new_particle.lifetime
+            });
         }
-        particle_builder.requests.clear();
     }
 }
@@ -1,23 +1,21 @@
 use specs::prelude::*;
-use crate::{gamelog::GameLog, WantsToPickupItem, Position, Name, InBackpack, text::capitalize};
+use crate::{events::{GameEvents, GameEvent}, WantsToPickupItem, Position, InBackpack};
 
 pub struct ItemPickupSystem {}
 
 impl<'a> System<'a> for ItemPickupSystem {
-    type SystemData = (WriteExpect<'a, GameLog>,
+    type SystemData = (WriteExpect<'a, GameEvents>,
                        WriteStorage<'a, WantsToPickupItem>,
                        WriteStorage<'a, Position>,
-                       ReadStorage<'a, Name>,
                        WriteStorage<'a, InBackpack>);
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut gamelog, mut wants_pickup, mut positions, names, mut backpack) = data;
+        let (mut events, mut wants_pickup, mut positions, mut backpack) = data;
 
         for pickup in wants_pickup.join() {
             positions.remove(pickup.item);
             backpack.insert(pickup.item, InBackpack{ owner: pickup.collected_by }).expect("Unable to insert backpack entry");
-            let picker_name = names.get(pickup.collected_by).unwrap();
-            gamelog.on(pickup.collected_by, &format!("{} {} up {}.", capitalize(&picker_name.np), picker_name.verb("picks", "pick"), names.get(pickup.item).unwrap().np));
+            events.single_write(GameEvent::PickedUp { entity: pickup.collected_by, item: pickup.item });
         }
 
         wants_pickup.clear();
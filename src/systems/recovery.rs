@@ -1,66 +1,106 @@
 use specs::prelude::*;
-use crate::{RunState, Turn, Player, Monster, Resting, Health, Stamina, Poise};
+use crate::{MyTurn, Turn, Resting, Health, Stamina, Poise, systems::initiative::REST_COST};
 
+/// Makes resting cost less than other actions. Whatever rests stays resting until its next turn,
+/// and `recover` gives it back more for each turn that passes in the meantime.
 pub struct RecoverySystem {}
 
 impl<'a> System<'a> for RecoverySystem {
     type SystemData = (
-        ReadExpect<'a, RunState>,
-        ReadExpect<'a, Turn>,
-        Entities<'a>,
-        ReadStorage<'a, Player>,
-        ReadStorage<'a, Monster>,
-        WriteStorage<'a, Resting>,
-        WriteStorage<'a, Health>,
-        WriteStorage<'a, Stamina>,
-        WriteStorage<'a, Poise>
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, Resting>
     );
 
     fn run(&mut self, data : Self::SystemData) {
-        let (
-            runstate,
-            turn,
-            entities,
-            players,
-            monsters,
-            mut resting,
-            mut health,
-            mut stamina,
-            mut poise
-        ) = data;
+        let (mut my_turns, resting) = data;
 
-        let mut to_stop_resting = Vec::new();
+        for (my_turn, _resting) in (&mut my_turns, &resting).join() {
+            my_turn.cost = REST_COST;
+        }
+    }
+}
 
-        for (entity, mut health, mut stamina, mut poise) in (&entities, &mut health, &mut stamina, &mut poise).join() {
-            if *runstate == RunState::PlayerTurn {
-                if let None = players.get(entity) {
-                    continue;
-                }
-            } else {
-                if let None = monsters.get(entity) {
-                    continue;
-                }
-            }
+/// What everything gets back for one turn of time passing, however fast it is and whatever it's
+/// doing, as long as it isn't resting.
+pub fn recover(ecs: &World, turn: Turn) {
+    let entities = ecs.entities();
+    let resting = ecs.read_storage::<Resting>();
+    let mut healths = ecs.write_storage::<Health>();
+    let mut staminas = ecs.write_storage::<Stamina>();
+    let mut poises = ecs.write_storage::<Poise>();
 
-            if let Some(_) = resting.get(entity) {
-                if *turn % 5 == 0 {
-                    health.health = i32::min(health.max_health, health.health + 1);
-                }
+    for (entity, health, stamina, poise) in (&entities, &mut healths, &mut staminas, &mut poises).join() {
+        if resting.get(entity).is_some() {
+            if turn % 5 == 0 {
+                health.health = i32::min(health.max_health, health.health + 1);
+            }
+            stamina.stamina = i32::min(stamina.max_stamina, stamina.stamina + 1);
+        } else {
+            if turn % 10 == 0 {
+                health.health = i32::min(health.max_health, health.health + 1);
+            }
+            if turn % 5 == 0 {
                 stamina.stamina = i32::min(stamina.max_stamina, stamina.stamina + 1);
-                to_stop_resting.push(entity);
-            } else {
-                if *turn % 10 == 0 {
-                    health.health = i32::min(health.max_health, health.health + 1);
-                }
-                if *turn % 5 == 0 {
-                    stamina.stamina = i32::min(stamina.max_stamina, stamina.stamina + 1);
-                    poise.poise = i32::min(poise.max_poise, poise.poise + 1);
-                }
+                poise.poise = i32::min(poise.max_poise, poise.poise + 1);
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+    use crate::testing::Fixture;
+    use crate::{Turn, Resting, Energy, Health, Stamina, Poise};
+    use crate::systems::initiative::{give_turns, EndTurnSystem, NORMAL_SPEED};
+    use super::RecoverySystem;
+
+    // Gives out turns until `turns` have passed, with the player resting on every one of theirs.
+    fn pass_turns(fixture: &mut Fixture, turns: Turn) {
+        while *fixture.ecs.fetch::<Turn>() < turns {
+            if give_turns(&mut fixture.ecs) {
+                let player = fixture.player();
+                fixture.insert(player, Resting {});
+            }
+            fixture.run(RecoverySystem {});
+            fixture.run(EndTurnSystem {});
+        }
+    }
+
+    fn worn_out(fixture: &mut Fixture, entity: Entity) {
+        fixture.insert(entity, Health { max_health: 100, health: 50 });
+        fixture.insert(entity, Stamina { max_stamina: 100, stamina: 50 });
+        fixture.insert(entity, Poise { max_poise: 100, poise: 50 });
+    }
+
+    #[test]
+    fn recovers_once_a_turn_however_often_it_acts() {
+        let mut fixture = Fixture::new("
+            #####
+            #@..#
+            #####");
+        let player = fixture.player();
+        worn_out(&mut fixture, player);
+        let fast = fixture.place(3, 1)
+            .with(Energy { energy: 0, speed: NORMAL_SPEED * 2 })
+            .build();
+        worn_out(&mut fixture, fast);
+        let slow = fixture.place(2, 1)
+            .with(Energy { energy: 0, speed: NORMAL_SPEED / 3 })
+            .build();
+        worn_out(&mut fixture, slow);
+
+        pass_turns(&mut fixture, 20);
 
-        for entity in to_stop_resting {
-            resting.remove(entity);
+        // Resting at half the cost of acting gets two turns for each one passing, but only one
+        // turn's worth of rest. The player has to wait out the first turn before they can rest.
+        assert_eq!(fixture.get::<Health>(player).unwrap().health, 54);
+        assert_eq!(fixture.get::<Stamina>(player).unwrap().stamina, 69);
+        assert_eq!(fixture.get::<Poise>(player).unwrap().poise, 50);
+        for entity in [fast, slow].iter() {
+            assert_eq!(fixture.get::<Health>(*entity).unwrap().health, 52);
+            assert_eq!(fixture.get::<Stamina>(*entity).unwrap().stamina, 54);
+            assert_eq!(fixture.get::<Poise>(*entity).unwrap().poise, 54);
         }
     }
 }
@@ -1,24 +1,32 @@
 use specs::prelude::*;
-use crate::{Map, ItemUseInProgress, SpreadsLiquid};
+use crate::{Map, TileType, ItemUseInProgress, SpreadsLiquid, events::{GameEvent, GameEvents}};
 
 pub struct SpreadLiquidSystem {}
 
 impl<'a> System<'a> for SpreadLiquidSystem {
     type SystemData = (
         WriteExpect<'a, Map>,
+        WriteExpect<'a, GameEvents>,
+        Entities<'a>,
         WriteStorage<'a, ItemUseInProgress>,
         ReadStorage<'a, SpreadsLiquid>
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, use_in_progress, liquid_spreaders) = data;
+        let (mut map, mut events, entities, use_in_progress, liquid_spreaders) = data;
 
-        for (useitem,) in (&use_in_progress,).join() {
+        for (entity, useitem) in (&entities, &use_in_progress).join() {
 
             if let Some(spreads_liquid)  = liquid_spreaders.get(useitem.item) {
                 let target_tile_idxs: Vec<usize> = useitem.target_tiles.iter().map(|t| map.xy_idx(t.x, t.y)).collect();
+                let mut tiles = 0;
                 for tile_idx in target_tile_idxs.iter() {
-                    map.stains[*tile_idx].insert(spreads_liquid.liquid);
+                    if map.stains[*tile_idx].insert(spreads_liquid.liquid) && map.tiles[*tile_idx] != TileType::Wall {
+                        tiles += 1;
+                    }
+                }
+                if tiles > 0 {
+                    events.single_write(GameEvent::Stained { entity, tiles });
                 }
             }
         }
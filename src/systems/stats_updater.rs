@@ -0,0 +1,139 @@
+use specs::prelude::*;
+use crate::{stats::Stats, events::{GameEvent, GameEvents, FleeReason}, ItemUseInProgress, Equippable};
+
+/// Counts up what the player has done from the events, and the items they're using.
+pub struct StatsUpdaterSystem {}
+
+impl<'a> System<'a> for StatsUpdaterSystem {
+    type SystemData = (
+        WriteExpect<'a, Stats>,
+        ReadExpect<'a, GameEvents>,
+        ReadExpect<'a, Entity>,
+        ReadStorage<'a, ItemUseInProgress>,
+        ReadStorage<'a, Equippable>
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut stats, events, player, use_in_progress, equippables) = data;
+        let player = *player;
+
+        let stats = &mut *stats;
+        for event in events.read(&mut stats.events) {
+            let run = &mut stats.run;
+            match event {
+                GameEvent::Died { entity, by: Some(by), .. } if *by == player && *entity != player => run.monsters_killed += 1,
+                GameEvent::Fled { entity, reason: FleeReason::Shamed, by: Some(by) } if *by == player && *entity != player => run.monsters_scared_off += 1,
+                GameEvent::Awed { entity, by, poise, .. } if *by == player && *entity != player => run.poise_inflicted += poise,
+                GameEvent::StartedDance { entity, dance } if *entity == player => *run.dances.entry(dance.name.clone()).or_insert(0) += 1,
+                GameEvent::Stained { entity, tiles } if *entity == player => run.tiles_stained += tiles,
+                _ => {}
+            }
+        }
+
+        // Equipping goes through using too, but doesn't count.
+        if let Some(useitem) = use_in_progress.get(player) {
+            if !equippables.contains(useitem.item) {
+                stats.run.items_used += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use specs::prelude::*;
+    use rltk::{Point};
+    use crate::testing::Fixture;
+    use crate::events::{GameEvent, GameEvents, FleeReason};
+    use crate::{stats::Stats, dancing::DanceDefs, liquids::Liquid, Map, ItemUseInProgress, Name, CombatStats, Health, Stamina, WantsToMelee, HurtBy};
+    use crate::systems::{MeleeCombatSystem, DamageSystem, SpreadLiquidSystem, damage_system::delete_the_dead};
+    use super::StatsUpdaterSystem;
+
+    #[test]
+    fn counts_only_what_the_player_did() {
+        let mut fixture = Fixture::new("
+            #####
+            #@..#
+            #####");
+        let player = fixture.player();
+        let rabbit = fixture.spawn("rabbit", 2, 1);
+        let hop = fixture.ecs.fetch::<DanceDefs>().get("hop").unwrap().clone();
+        {
+            let mut events = fixture.ecs.write_resource::<GameEvents>();
+            let at = Point::new(2, 1);
+            events.single_write(GameEvent::Died { entity: rabbit, name: Name::new_regular("rabbit"), at, by: Some(player) });
+            events.single_write(GameEvent::Died { entity: player, name: Name::new_regular("you"), at, by: Some(rabbit) });
+            events.single_write(GameEvent::Fled { entity: rabbit, reason: FleeReason::Shamed, by: Some(player) });
+            events.single_write(GameEvent::Fled { entity: rabbit, reason: FleeReason::Shamed, by: None });
+            events.single_write(GameEvent::Fled { entity: rabbit, reason: FleeReason::Hurt, by: Some(player) });
+            events.single_write(GameEvent::Awed { entity: rabbit, by: player, reason: "a hop".to_string(), poise: 3 });
+            events.single_write(GameEvent::Awed { entity: player, by: rabbit, reason: "a hop".to_string(), poise: 5 });
+            events.single_write(GameEvent::StartedDance { entity: player, dance: hop.clone() });
+            events.single_write(GameEvent::StartedDance { entity: player, dance: hop.clone() });
+            events.single_write(GameEvent::StartedDance { entity: rabbit, dance: hop });
+        }
+
+        fixture.run(StatsUpdaterSystem{});
+
+        let stats = fixture.ecs.fetch::<Stats>().run.clone();
+        assert_eq!(stats.monsters_killed, 1);
+        assert_eq!(stats.monsters_scared_off, 1);
+        assert_eq!(stats.poise_inflicted, 3);
+        assert_eq!(stats.dances.into_iter().collect::<Vec<_>>(), vec![("hop".to_string(), 2)]);
+    }
+
+    #[test]
+    fn doesnt_count_monsters_killed_by_others() {
+        let mut fixture = Fixture::new("
+            ######
+            #@...#
+            ######");
+        let player = fixture.player();
+        let rabbit = fixture.spawn("rabbit", 2, 1);
+        let victim = fixture.place(3, 1)
+            .with(Name::new_regular("thing"))
+            .with(CombatStats{ defence: 0, power: 0 })
+            .with(Health{ max_health: 1, health: 1 })
+            .with(Stamina{ stamina: 1, max_stamina: 1 })
+            .build();
+        fixture.insert(rabbit, WantsToMelee{ target: victim });
+        // The player hurt it first, but the rabbit finished it off.
+        fixture.insert(victim, HurtBy{ by: player });
+
+        fixture.run(MeleeCombatSystem{});
+        fixture.run(DamageSystem{});
+        delete_the_dead(&mut fixture.ecs);
+        fixture.run(StatsUpdaterSystem{});
+
+        assert!(!fixture.ecs.is_alive(victim));
+        assert_eq!(fixture.ecs.fetch::<Stats>().run.monsters_killed, 0);
+    }
+
+    #[test]
+    fn counts_items_used_and_tiles_stained() {
+        let mut fixture = Fixture::new("
+            #####
+            #@..#
+            #####");
+        let player = fixture.player();
+        let balloon = fixture.spawn("water balloon", 1, 1);
+        {
+            let mut map = fixture.ecs.fetch_mut::<Map>();
+            let idx = map.xy_idx(3, 1);
+            map.stains[idx].insert(Liquid::WATER);
+        }
+        // Only the floor at 2,1 wasn't wet already.
+        let target_tiles = vec![Point::new(2, 1), Point::new(3, 1), Point::new(4, 1)];
+        fixture.insert(player, ItemUseInProgress{ item: balloon, targets_centre: Point::new(3, 1), target_tiles, targets: Vec::new() });
+
+        fixture.run(SpreadLiquidSystem{});
+        fixture.run(StatsUpdaterSystem{});
+        // Splashing the same tiles again doesn't stain any more of them.
+        fixture.run(SpreadLiquidSystem{});
+        fixture.run(StatsUpdaterSystem{});
+
+        let stats = fixture.ecs.fetch::<Stats>().run.clone();
+        assert_eq!(stats.items_used, 2);
+        assert_eq!(stats.tiles_stained, 1);
+    }
+}
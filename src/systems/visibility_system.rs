@@ -19,6 +19,8 @@ impl<'a> System<'a> for VisibilitySystem {
                 viewshed.visible_tiles.clear();
                 viewshed.visible_tiles = field_of_view(Point::new(pos.x, pos.y), viewshed.range, &*map);
                 viewshed.visible_tiles.retain(|p| p.x >= 0 && p.x < map.width && p.y >= 0 && p.y < map.height);
+                // field_of_view comes out of a HashSet, so put it in a fixed order for anything picking from it at random
+                viewshed.visible_tiles.sort_by_key(|p| (p.y, p.x));
                 let p: Option<&Player> = player.get(ent);
                 if let Some(_p) = p {
                     for t in map.visible_tiles.iter_mut() {
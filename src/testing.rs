@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+use specs::prelude::*;
+use specs::shrev::ReaderId;
+use rltk::{Point};
+use crate::{setup_ecs, Map, Rect, TileType, KeyState, Position, state::Turn, stuff::{self, StuffDefs}, dancing::DanceDefs, random::RandomNumberGenerator};
+use crate::events::{GameEvent, GameEvents};
+use crate::gamelog::{GameLog, PlayerLog};
+use crate::stats::Stats;
+use crate::systems::{VisibilitySystem, LogUpdaterSystem, particle_system::ParticleBuilder, auto_movement_system::AutoMoveRules};
+
+/// Builds a map drawn in text, one row per line, with leading spaces ignored so it can be
+/// indented along with the test. `#` is wall, `.` floor and `>` the stairs down; `@` is floor
+/// where the player starts, and there has to be exactly one. It's made the middle of the only
+/// room, so the map looks to everything else as if the player started there.
+pub fn draw_map(drawing: &str) -> Map {
+    let rows: Vec<&str> = drawing.lines().map(|row| row.trim()).filter(|row| !row.is_empty()).collect();
+    let width = rows[0].len();
+    let height = rows.len();
+    let mut map = Map {
+        tiles: Vec::new(),
+        rooms: Vec::new(),
+        width: width as i32,
+        height: height as i32,
+        revealed_tiles: vec![false; width * height],
+        visible_tiles: vec![false; width * height],
+        blocked: vec![false; width * height],
+        depth: 1,
+        stains: vec![HashSet::new(); width * height],
+        tile_content: vec![Vec::new(); width * height]
+    };
+    for (y, row) in rows.iter().enumerate() {
+        assert_eq!(row.len(), width, "row {} of the map is a different width", y);
+        for (x, c) in row.chars().enumerate() {
+            map.tiles.push(match c {
+                '#' => TileType::Wall,
+                '.' => TileType::Floor,
+                '>' => TileType::DownStairs,
+                '@' => {
+                    map.rooms.push(Rect::new(x as i32 - 1, y as i32 - 1, 2, 2));
+                    TileType::Floor
+                }
+                _ => panic!("no tile for {:?} in the map", c)
+            });
+        }
+    }
+    assert_eq!(map.rooms.len(), 1, "the map needs one @ for the player");
+    map.populate_blocked();
+    map
+}
+
+/// A small world for testing systems one at a time. Everything a system might fetch is there,
+/// the random number generator is always seeded the same, and nothing runs unless a test runs it.
+pub struct Fixture {
+    pub ecs: World,
+    events: ReaderId<GameEvent>
+}
+
+impl Fixture {
+    /// Builds a world on a map drawn as for `draw_map`, with the player on the `@`.
+    pub fn new(drawing: &str) -> Self {
+        let map = draw_map(drawing);
+        let (player_x, player_y) = map.rooms[0].centre();
+
+        let mut ecs = World::new();
+        setup_ecs(&mut ecs);
+        let stuff = StuffDefs::load().unwrap();
+        ecs.insert(DanceDefs::load(&stuff).unwrap());
+        ecs.insert(stuff);
+        ecs.insert(RandomNumberGenerator::seeded(0));
+        ecs.insert(KeyState{ requested_auto_move: false });
+        ecs.insert(ParticleBuilder::new());
+        ecs.insert(AutoMoveRules::default());
+        ecs.insert(PlayerLog::new());
+        let mut events = GameEvents::new();
+        let reader = events.register_reader();
+        ecs.insert(GameLog::new(&mut events));
+        ecs.insert(Stats::new(&mut events));
+        ecs.insert(events);
+        ecs.insert::<Turn>(0);
+        ecs.insert(map);
+
+        let player = stuff::player(&mut ecs, player_x, player_y);
+        ecs.insert(Point::new(player_x, player_y));
+        ecs.insert(player);
+
+        Fixture { ecs, events: reader }
+    }
+
+    pub fn player(&self) -> Entity {
+        *self.ecs.fetch::<Entity>()
+    }
+
+    /// Spawns something from `stuff.json`, just as the game would.
+    pub fn spawn(&mut self, name: &str, x: i32, y: i32) -> Entity {
+        stuff::spawn(&mut self.ecs, name, x, y)
+    }
+
+    /// Starts an entity at a spot on the map, for giving it exactly the components a test needs.
+    pub fn place(&mut self, x: i32, y: i32) -> EntityBuilder<'_> {
+        self.ecs.create_entity().with(Position{ x, y })
+    }
+
+    pub fn insert<C: Component>(&mut self, entity: Entity, component: C) {
+        self.ecs.write_storage::<C>().insert(entity, component).expect("Unable to insert component");
+    }
+
+    pub fn run<S: for<'a> System<'a>>(&mut self, mut system: S) {
+        system.run_now(&self.ecs);
+        self.ecs.maintain();
+    }
+
+    pub fn get<C: Component + Clone>(&self, entity: Entity) -> Option<C> {
+        self.ecs.read_storage::<C>().get(entity).cloned()
+    }
+
+    /// The events written since the last call.
+    pub fn events(&mut self) -> Vec<GameEvent> {
+        self.ecs.fetch::<GameEvents>().read(&mut self.events).cloned().collect()
+    }
+
+    /// Everything in the player's log, after bringing it up to date with what the player can see.
+    pub fn log(&mut self) -> Vec<String> {
+        self.run(VisibilitySystem{});
+        self.run(LogUpdaterSystem{});
+        self.ecs.fetch::<PlayerLog>().entries.iter().map(|entry| entry.text()).collect()
+    }
+}
@@ -1,3 +1,20 @@
+/// A time in seconds since the Unix epoch as a UTC date and time, to the minute.
+pub fn time_text(secs: u64) -> String {
+    // Days to a civil date, after Howard Hinnant's `civil_from_days`.
+    let days = (secs / 86400) as i64;
+    let minutes = (secs % 86400) / 60;
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, minutes / 60, minutes % 60)
+}
+
 pub fn capitalize<S: ToString>(string: &S) -> String {
     let string = string.to_string();
     let mut chars = string.chars();
@@ -6,3 +23,26 @@ pub fn capitalize<S: ToString>(string: &S) -> String {
         Some(c) => c.to_uppercase().to_string() + chars.as_str()
     }
 }
+
+/// Splits text into lines of at most `width` characters, breaking between words where it can.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+        while line.chars().count() > width {
+            let rest = line.split_off(line.char_indices().nth(width).map_or(line.len(), |(i, _)| i));
+            lines.push(std::mem::replace(&mut line, rest));
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
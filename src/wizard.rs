@@ -0,0 +1,232 @@
+use rltk::{RGB, Rltk, Point, VirtualKeyCode, Rect};
+use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, SimpleMarker};
+use super::{state::State, stuff::{self, StuffDefs}, systems::{VisibilitySystem, MapIndexingSystem}, drawing, gui::key_char, keymap::Keymap, player::PlayerCommand, Map, RunState, TileType};
+use super::components::*;
+
+const CONSOLE_HEIGHT: i32 = 16;
+
+const HELP: &[&str] = &[
+    "spawn <stuff>            spawn stuff at the cursor",
+    "reveal                   reveal the whole map",
+    "set <health|poise|stamina> <n>",
+    "teleport                 move the player to the cursor",
+    "descend                  go down to the next floor",
+    "dump                     show the components of everything at the cursor",
+    "help                     show this again"
+];
+
+/// The debug console, only in debug builds. Nothing done here is recorded, so a game it has
+/// changed is marked as one that replays can't be made of.
+pub struct Console {
+    input: String,
+    output: Vec<String>
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Console {
+            input: String::new(),
+            output: vec!["Debug console. Type help for commands, escape to close.".to_string()]
+        }
+    }
+}
+
+pub fn show_console(gs: &mut State, ctx: &mut Rltk) -> Option<RunState> {
+    let (screen_width, _) = ctx.get_char_size();
+
+    let fg = RGB::from_u8(192, 192, 192);
+    let input_fg = RGB::from_u8(255, 255, 255);
+    let bg = RGB::from_u8(16, 16, 32);
+    let cursor_bg = RGB::from_u8(128, 0, 128);
+
+    let (mouse_x, mouse_y) = ctx.mouse_pos();
+    let cursor = drawing::screen_to_world_point(Point::new(mouse_x, mouse_y), &gs.ecs, ctx);
+    if mouse_y >= CONSOLE_HEIGHT {
+        ctx.set_bg(mouse_x, mouse_y, cursor_bg);
+    }
+
+    let console = &mut gs.console;
+    ctx.fill_region(Rect::with_size(0, 0, screen_width, CONSOLE_HEIGHT as u32), rltk::to_cp437(' '), fg, bg);
+    let shown = (CONSOLE_HEIGHT - 1) as usize;
+    for (y, line) in console.output.iter().rev().take(shown).rev().enumerate() {
+        let line: String = line.chars().take(screen_width as usize).collect();
+        ctx.print_color(0, y as i32, fg, bg, line);
+    }
+    ctx.print_color(0, CONSOLE_HEIGHT - 1, input_fg, bg, format!("> {}_", console.input));
+
+    let key = ctx.key?;
+    // The key that opened the console closes it too, unless it's needed for typing.
+    let closes = gs.ecs.fetch::<Keymap>().command(key) == Some(PlayerCommand::ShowConsole) && key_char(key, ctx.shift).is_none();
+    match key {
+        VirtualKeyCode::Escape => return Some(RunState::AwaitingInput),
+        _ if closes => return Some(RunState::AwaitingInput),
+        VirtualKeyCode::Back => { console.input.pop(); }
+        VirtualKeyCode::Return => {
+            let line = std::mem::take(&mut console.input);
+            console.output.push(format!("> {}", line));
+            let mut output = Vec::new();
+            let result = run_command(&mut gs.ecs, cursor, line.trim(), &mut output);
+            gs.console.output.extend(output);
+            if changes_game(&line) {
+                gs.taint_recording();
+            }
+            return result;
+        }
+        _ => {
+            if let Some(c) = key_char(key, ctx.shift) {
+                console.input.push(c);
+            }
+        }
+    }
+    None
+}
+
+// Whether a command can change how the game plays out from here, which the replay can't follow.
+fn changes_game(line: &str) -> bool {
+    matches!(line.split_whitespace().next(), Some("spawn") | Some("reveal") | Some("set") | Some("teleport") | Some("descend"))
+}
+
+fn run_command(ecs: &mut World, cursor: Point, line: &str, output: &mut Vec<String>) -> Option<RunState> {
+    let mut words = line.split_whitespace();
+    let command = words.next().unwrap_or("");
+    let args: Vec<&str> = words.collect();
+    let result = match command {
+        "" => Ok(()),
+        "help" => {
+            output.extend(HELP.iter().map(|s| s.to_string()));
+            Ok(())
+        }
+        "spawn" => spawn(ecs, cursor, &args.join(" "), output),
+        "reveal" => {
+            let mut map = ecs.fetch_mut::<Map>();
+            for revealed in map.revealed_tiles.iter_mut() {
+                *revealed = true;
+            }
+            Ok(())
+        }
+        "set" => set(ecs, &args, output),
+        "teleport" => teleport(ecs, cursor),
+        "descend" => return Some(RunState::NextLevel),
+        "dump" => dump(ecs, cursor, output),
+        _ => Err(format!("Unknown command {}. Try help.", command))
+    };
+    if let Err(e) = result {
+        output.push(e);
+    }
+    None
+}
+
+fn cursor_on_map(ecs: &World, cursor: Point) -> Result<(), String> {
+    if ecs.fetch::<Map>().point_valid(&cursor) {
+        Ok(())
+    } else {
+        Err("The cursor isn't on the map.".to_string())
+    }
+}
+
+fn spawn(ecs: &mut World, cursor: Point, name: &str, output: &mut Vec<String>) -> Result<(), String> {
+    cursor_on_map(ecs, cursor)?;
+    match ecs.fetch::<StuffDefs>().get(name) {
+        None => return Err(format!("No stuff called {}.", name)),
+        Some(def) if def.player => return Err("There can only be one player.".to_string()),
+        Some(_) => {}
+    }
+    let entity = stuff::spawn(ecs, name, cursor.x, cursor.y);
+    output.push(format!("Spawned {} as entity {}.", name, entity.id()));
+    Ok(())
+}
+
+fn set(ecs: &mut World, args: &[&str], output: &mut Vec<String>) -> Result<(), String> {
+    let (stat, value) = match args {
+        [stat, value] => (*stat, value.parse::<i32>().map_err(|_| format!("{} isn't a number.", value))?),
+        _ => return Err("Usage: set <health|poise|stamina> <n>".to_string())
+    };
+    let player = *ecs.fetch::<Entity>();
+    // Going over the maximum raises it, so there's no need to set that separately.
+    match stat {
+        Health::NAME => {
+            let mut healths = ecs.write_storage::<Health>();
+            let health = healths.get_mut(player).ok_or("The player has no health.")?;
+            health.health = value;
+            health.max_health = i32::max(health.max_health, value);
+        }
+        Poise::NAME => {
+            let mut poises = ecs.write_storage::<Poise>();
+            let poise = poises.get_mut(player).ok_or("The player has no poise.")?;
+            poise.poise = value;
+            poise.max_poise = i32::max(poise.max_poise, value);
+        }
+        Stamina::NAME => {
+            let mut staminas = ecs.write_storage::<Stamina>();
+            let stamina = staminas.get_mut(player).ok_or("The player has no stamina.")?;
+            stamina.stamina = value;
+            stamina.max_stamina = i32::max(stamina.max_stamina, value);
+        }
+        _ => return Err(format!("Can't set {}.", stat))
+    }
+    output.push(format!("Set {} to {}.", stat, value));
+    Ok(())
+}
+
+fn teleport(ecs: &mut World, cursor: Point) -> Result<(), String> {
+    cursor_on_map(ecs, cursor)?;
+    {
+        // The map's blocked tiles don't know about anything spawned from here yet.
+        let map = ecs.fetch::<Map>();
+        let positions = ecs.read_storage::<Position>();
+        let blockers = ecs.read_storage::<BlocksTile>();
+        let occupied = (&positions, &blockers).join().any(|(pos, _)| pos.x == cursor.x && pos.y == cursor.y);
+        if map.tiles[map.point_idx(&cursor)] == TileType::Wall || occupied {
+            return Err("Something's in the way.".to_string());
+        }
+    }
+    {
+        let player = *ecs.fetch::<Entity>();
+        *ecs.write_resource::<Point>() = cursor;
+        if let Some(pos) = ecs.write_storage::<Position>().get_mut(player) {
+            pos.x = cursor.x;
+            pos.y = cursor.y;
+        }
+        if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(player) {
+            viewshed.dirty = true;
+        }
+    }
+    // Nothing gets a turn while the console is open, so bring the map up to date now.
+    MapIndexingSystem{}.run_now(ecs);
+    VisibilitySystem{}.run_now(ecs);
+    Ok(())
+}
+
+// Shows each `saved` component the way it would be saved.
+macro_rules! dump_components {
+    ($ecs:expr, $entity:expr, $output:expr, [ $( $type:ident )* ]) => {
+        let markers = $ecs.read_storage::<SimpleMarker<SerializeMe>>();
+        $(
+            if let Some(component) = $ecs.read_storage::<$type>().get($entity) {
+                let data = ConvertSaveload::<SimpleMarker<SerializeMe>>::convert_into(component, |e| markers.get(e).cloned()).unwrap();
+                $output.push(format!("  {} {}", stringify!($type), serde_json::to_string(&data).unwrap_or_default()));
+            }
+        )*
+    };
+}
+
+fn dump(ecs: &mut World, cursor: Point, output: &mut Vec<String>) -> Result<(), String> {
+    cursor_on_map(ecs, cursor)?;
+    let here: Vec<Entity> = {
+        let entities = ecs.entities();
+        let positions = ecs.read_storage::<Position>();
+        (&entities, &positions).join()
+            .filter(|(_, pos)| pos.x == cursor.x && pos.y == cursor.y)
+            .map(|(entity, _)| entity)
+            .collect()
+    };
+    if here.is_empty() {
+        return Err(format!("Nothing at ({}, {}).", cursor.x, cursor.y));
+    }
+    for entity in here {
+        output.push(format!("Entity {}:", entity.id()));
+        with_components!(dump_components!(ecs, entity, output,));
+    }
+    Ok(())
+}